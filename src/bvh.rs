@@ -1,29 +1,106 @@
 use super::aabb::AABB;
 use super::hit::{Hit, Hittable};
 use super::ray::Ray;
+use super::vec3::Vec3;
 use rand::Rng;
+use rand_pcg::Pcg64Mcg;
 use std::cmp::Ordering;
-use std::rc::Rc;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::Arc;
 
+// How objects are divided between a node's two children
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Split {
+    // A random axis, split at the median of the boxes' min corners. Quick to build, slow to trace when
+    // objects are very different sizes.
+    Random,
+    // The axis and position with the lowest surface area heuristic cost, out of a few bins along each axis
+    SurfaceArea,
+}
+
+impl FromStr for Split {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "random" => Ok(Self::Random),
+            "sah" => Ok(Self::SurfaceArea),
+            _ => Err(String::from("expected sah or random")),
+        }
+    }
+}
+
+// Bins along each axis that split positions are chosen between
+const SAH_BINS: usize = 16;
+// Objects a leaf holds at most, when splitting them further isn't worth it
+const MAX_LEAF_SIZE: usize = 4;
+// Cost of testing a ray against a child's box, relative to testing it against an object
+const TRAVERSAL_COST: f64 = 0.125;
+// Below this the surface area builder only splits in half, so no BVH is deeper than this plus log2 of its objects
+const MAX_SAH_DEPTH: usize = 32;
+// Far children waiting to be visited by a flat BVH's traversal, at most one for each level above the current node
+const STACK_SIZE: usize = 64;
+
+// A tree of boxes around objects, see FlatBVH for the layout scenes are traced with
 pub struct BVH {
-    left: Rc<dyn Hittable>,
-    right: Rc<dyn Hittable>,
+    left: Child,
+    right: Child,
     bounding_box: AABB,
+    // The axis the children were split along, the left one is nearer the low end
+    axis: u8,
+}
+
+enum Child {
+    // A lone object is tested straight away, its own hit test is about as quick as its box's
+    Object(Arc<dyn Hittable>, AABB),
+    Leaf(Leaf),
+    Node(Box<BVH>),
 }
 
 impl BVH {
-    pub fn new<T: Rng>(rng: &mut T, mut objects: Vec<Rc<dyn Hittable>>, t0: f64, t1: f64) -> Self {
+    pub fn new<T: Rng>(
+        rng: &mut T,
+        objects: Vec<Arc<dyn Hittable>>,
+        t0: f64,
+        t1: f64,
+        split: Split,
+    ) -> Self {
+        match split {
+            Split::Random => Self::random_split(rng, objects, t0, t1),
+            Split::SurfaceArea => {
+                let boxed = objects
+                    .into_iter()
+                    .map(|object| Boxed::new(object, t0, t1))
+                    .collect();
+                Self::surface_area_split(boxed, 0)
+            }
+        }
+    }
+
+    fn random_split<T: Rng>(
+        rng: &mut T,
+        mut objects: Vec<Arc<dyn Hittable>>,
+        t0: f64,
+        t1: f64,
+    ) -> Self {
         let axis: u8 = rng.gen_range(0, 3);
-        let comparator = |a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>| compare(a, b, axis);
+        let comparator = |a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>| compare(a, b, axis);
         let len = objects.len();
+        let object = |o: &Arc<dyn Hittable>| {
+            let bounding_box = o
+                .bounding_box(t0, t1)
+                .expect("No bounding box in BVH construction");
+            Child::Object(Arc::clone(o), bounding_box)
+        };
 
-        let (left, right): (Rc<dyn Hittable>, Rc<dyn Hittable>) = match len.cmp(&2) {
-            Ordering::Less => (objects[0].clone(), objects[0].clone()),
+        let (left, right) = match len.cmp(&2) {
+            Ordering::Less => (object(&objects[0]), object(&objects[0])),
             Ordering::Equal => {
                 if comparator(&objects[0], &objects[1]) == Ordering::Less {
-                    (objects[0].clone(), objects[1].clone())
+                    (object(&objects[0]), object(&objects[1]))
                 } else {
-                    (objects[1].clone(), objects[0].clone())
+                    (object(&objects[1]), object(&objects[0]))
                 }
             }
             Ordering::Greater => {
@@ -31,36 +108,61 @@ impl BVH {
 
                 let right_half = objects.split_off(len / 2);
                 (
-                    Rc::new(BVH::new(rng, objects, t0, t1)),
-                    Rc::new(BVH::new(rng, right_half, t0, t1)),
+                    Child::Node(Box::new(Self::random_split(rng, objects, t0, t1))),
+                    Child::Node(Box::new(Self::random_split(rng, right_half, t0, t1))),
                 )
             }
         };
 
-        let box_left = left.bounding_box(t0, t1);
-        let box_right = right.bounding_box(t0, t1);
-        if box_left.is_none() || box_right.is_none() {
-            panic!("No bounding box in BVH construction");
-        }
-
         BVH {
+            bounding_box: left.bounding_box().surrounding_box(&right.bounding_box()),
             left,
             right,
-            bounding_box: box_left.unwrap().surrounding_box(&box_right.unwrap()),
+            axis,
+        }
+    }
+
+    // Always splits at the top, a scene with a few objects still gets a node around them
+    fn surface_area_split(mut objects: Vec<Boxed>, depth: usize) -> Self {
+        if objects.len() == 1 {
+            let only = objects.remove(0);
+            return BVH {
+                left: Child::Object(Arc::clone(&only.object), only.bounding_box),
+                right: Child::Object(only.object, only.bounding_box),
+                bounding_box: only.bounding_box,
+                axis: 0,
+            };
+        }
+
+        let best = best_split(&objects);
+        Self::split_at(objects, best, depth)
+    }
+
+    fn split_at(mut objects: Vec<Boxed>, best: Option<BinSplit>, depth: usize) -> Self {
+        let bounding_box = surrounding(&objects, |o| o.bounding_box);
+        let (axis, right) = partition(&mut objects, best);
+        BVH {
+            left: surface_area_child(objects, depth + 1),
+            right: surface_area_child(right, depth + 1),
+            bounding_box,
+            axis,
         }
     }
 }
 
 impl Hittable for BVH {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
         if !self.bounding_box.hit(r, t_min, t_max) {
             return None;
         }
 
-        let hit_left = self.left.hit(r, t_min, t_max);
-        let hit_right = self
-            .right
-            .hit(r, t_min, if let Some(l) = &hit_left { l.t } else { t_max });
+        let hit_left = self.left.hit(r, t_min, t_max, rng);
+        let hit_right = self.right.hit(
+            r,
+            t_min,
+            if let Some(l) = &hit_left { l.t } else { t_max },
+            rng,
+        );
 
         if let Some(r) = hit_right {
             Some(r)
@@ -74,12 +176,532 @@ impl Hittable for BVH {
     }
 }
 
-fn compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>, axis: u8) -> Ordering {
+impl Child {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        match self {
+            Self::Object(object, _) => object.hit(r, t_min, t_max, rng),
+            Self::Leaf(leaf) => leaf.hit(r, t_min, t_max, rng),
+            Self::Node(node) => node.hit(r, t_min, t_max, rng),
+        }
+    }
+
+    fn bounding_box(&self) -> AABB {
+        match self {
+            Self::Object(_, bounding_box) => *bounding_box,
+            Self::Leaf(leaf) => leaf.bounding_box,
+            Self::Node(node) => node.bounding_box,
+        }
+    }
+}
+
+fn compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: u8) -> Ordering {
     let box_a = a.bounding_box(0.0, 0.0);
     let box_b = b.bounding_box(0.0, 0.0);
     if box_a.is_none() || box_b.is_none() {
         panic!("No bounding box in BVH construction");
     }
 
-    box_b.unwrap().min[axis].total_cmp(&box_a.unwrap().min[axis])
+    box_a.unwrap().min[axis].total_cmp(&box_b.unwrap().min[axis])
+}
+
+// A BVH laid out in one array, each interior node followed by its first child, and traced without recursion or
+// virtual calls until it reaches objects
+pub struct FlatBVH {
+    nodes: Vec<Node>,
+    // Every leaf's objects, next to each other
+    objects: Vec<Arc<dyn Hittable>>,
+}
+
+struct Node {
+    bounding_box: AABB,
+    // An interior node's second child, or a leaf's first object
+    offset: u32,
+    // Objects in a leaf, 0 for interior nodes
+    count: u16,
+    axis: u8,
+}
+
+impl FlatBVH {
+    pub fn new<T: Rng>(
+        rng: &mut T,
+        objects: Vec<Arc<dyn Hittable>>,
+        t0: f64,
+        t1: f64,
+        split: Split,
+    ) -> Self {
+        BVH::new(rng, objects, t0, t1, split).into()
+    }
+
+    fn push_node(&mut self, bvh: BVH) {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounding_box: bvh.bounding_box,
+            offset: 0,
+            count: 0,
+            axis: bvh.axis,
+        });
+        self.push_child(bvh.left);
+        self.nodes[index].offset = index_u32(self.nodes.len());
+        self.push_child(bvh.right);
+    }
+
+    fn push_child(&mut self, child: Child) {
+        let (bounding_box, objects) = match child {
+            Child::Node(bvh) => return self.push_node(*bvh),
+            Child::Object(object, bounding_box) => (bounding_box, vec![object]),
+            Child::Leaf(leaf) => (leaf.bounding_box, leaf.objects),
+        };
+        self.nodes.push(Node {
+            bounding_box,
+            offset: index_u32(self.objects.len()),
+            count: u16::try_from(objects.len()).expect("too many objects in a BVH leaf"),
+            axis: 0,
+        });
+        self.objects.extend(objects);
+    }
+}
+
+impl From<BVH> for FlatBVH {
+    fn from(bvh: BVH) -> Self {
+        let mut flat = Self {
+            nodes: vec![],
+            objects: vec![],
+        };
+        flat.push_node(bvh);
+        flat
+    }
+}
+
+impl Hittable for FlatBVH {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        let negative = [
+            r.direction.x < 0.0,
+            r.direction.y < 0.0,
+            r.direction.z < 0.0,
+        ];
+        let mut stack = [0_u32; STACK_SIZE];
+        let mut pending = 0;
+        let mut current = 0;
+        let mut closest = t_max;
+        let mut hit = None;
+
+        loop {
+            let node = &self.nodes[current as usize];
+            // like the tree, a lone object is tested without its box
+            if node.count == 1 || node.bounding_box.hit(r, t_min, closest) {
+                if node.count == 0 {
+                    // the child nearer the ray's origin first, so hits in it cut the far one short
+                    let (near, far) = if negative[usize::from(node.axis)] {
+                        (node.offset, current + 1)
+                    } else {
+                        (current + 1, node.offset)
+                    };
+                    stack[pending] = far;
+                    pending += 1;
+                    current = near;
+                    continue;
+                }
+
+                let first = node.offset as usize;
+                for object in &self.objects[first..first + usize::from(node.count)] {
+                    if let Some(object_hit) = object.hit(r, t_min, closest, rng) {
+                        closest = object_hit.t;
+                        hit = Some(object_hit);
+                    }
+                }
+            }
+
+            if pending == 0 {
+                return hit;
+            }
+            pending -= 1;
+            current = stack[pending];
+        }
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.nodes[0].bounding_box)
+    }
+}
+
+fn index_u32(index: usize) -> u32 {
+    u32::try_from(index).expect("too many BVH nodes or objects")
+}
+
+// An object with its box worked out once, since the surface area builder looks at every box many times
+struct Boxed {
+    object: Arc<dyn Hittable>,
+    bounding_box: AABB,
+    centroid: Vec3,
+}
+
+impl Boxed {
+    fn new(object: Arc<dyn Hittable>, t0: f64, t1: f64) -> Self {
+        let bounding_box = object
+            .bounding_box(t0, t1)
+            .expect("No bounding box in BVH construction");
+        Self {
+            object,
+            bounding_box,
+            centroid: (bounding_box.min + bounding_box.max) * 0.5,
+        }
+    }
+}
+
+// A few objects tested one after the other, at the bottom of a surface area BVH
+struct Leaf {
+    objects: Vec<Arc<dyn Hittable>>,
+    bounding_box: AABB,
+}
+
+impl Leaf {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        if !self.bounding_box.hit(r, t_min, t_max) {
+            return None;
+        }
+
+        let mut hit = None;
+        let mut closest = t_max;
+        for object in &self.objects {
+            if let Some(object_hit) = object.hit(r, t_min, closest, rng) {
+                closest = object_hit.t;
+                hit = Some(object_hit);
+            }
+        }
+        hit
+    }
+}
+
+// A single object as it is, a leaf if splitting costs more than testing everything, or a node
+fn surface_area_child(mut objects: Vec<Boxed>, depth: usize) -> Child {
+    if objects.len() == 1 {
+        let only = objects.remove(0);
+        return Child::Object(only.object, only.bounding_box);
+    }
+
+    // past the depth limit every split is in half, which makes a leaf once few enough objects are left
+    let best = if depth < MAX_SAH_DEPTH {
+        best_split(&objects)
+    } else {
+        None
+    };
+    if objects.len() <= MAX_LEAF_SIZE {
+        let leaf_is_cheaper = match best {
+            Some(split) => split.cost >= leaf_cost(&objects),
+            None => true,
+        };
+        if leaf_is_cheaper {
+            return Child::Leaf(Leaf {
+                bounding_box: surrounding(&objects, |o| o.bounding_box),
+                objects: objects.into_iter().map(|o| o.object).collect(),
+            });
+        }
+    }
+    Child::Node(Box::new(BVH::split_at(objects, best, depth)))
+}
+
+// Splits objects at the cheapest split, or in half when their centers are too close together to bin, and
+// returns the axis and the right side's
+fn partition(objects: &mut Vec<Boxed>, best: Option<BinSplit>) -> (u8, Vec<Boxed>) {
+    if let Some(split) = best {
+        let (left, right): (Vec<Boxed>, Vec<Boxed>) = objects
+            .drain(..)
+            .partition(|o| split.bin(o.centroid[split.axis]) < split.boundary);
+        *objects = left;
+        return (split.axis, right);
+    }
+
+    let axis = widest_axis(&surrounding(objects, |o| AABB {
+        min: o.centroid,
+        max: o.centroid,
+    }));
+    objects.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    let half = objects.len() / 2;
+    (axis, objects.split_off(half))
+}
+
+// Objects split by which of the bins along an axis their centers fall in
+#[derive(Clone, Copy)]
+struct BinSplit {
+    axis: u8,
+    start: f64,
+    width: f64,
+    // the first bin on the right
+    boundary: usize,
+    cost: f64,
+}
+
+impl BinSplit {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn bin(&self, value: f64) -> usize {
+        (((value - self.start) / self.width) as usize).min(SAH_BINS - 1)
+    }
+}
+
+// The cheapest way to split by binned centers, None if every center is in the same place
+fn best_split(objects: &[Boxed]) -> Option<BinSplit> {
+    let centroids = surrounding(objects, |o| AABB {
+        min: o.centroid,
+        max: o.centroid,
+    });
+    let parent_area = surrounding(objects, |o| o.bounding_box).surface_area();
+
+    let mut best: Option<BinSplit> = None;
+    for axis in 0..3 {
+        let start = centroids.min[axis];
+        let extent = centroids.max[axis] - start;
+        if extent <= 0.0 {
+            continue;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let width = extent / SAH_BINS as f64;
+
+        let binned = BinSplit {
+            axis,
+            start,
+            width,
+            boundary: 0,
+            cost: 0.0,
+        };
+        let mut counts = [0_usize; SAH_BINS];
+        let mut boxes: [Option<AABB>; SAH_BINS] = [None; SAH_BINS];
+        for o in objects {
+            let bin = binned.bin(o.centroid[axis]);
+            counts[bin] += 1;
+            boxes[bin] =
+                Some(boxes[bin].map_or(o.bounding_box, |b| b.surrounding_box(&o.bounding_box)));
+        }
+
+        // the area and count on the right of each boundary, swept from the far end
+        let mut right_area = [0.0; SAH_BINS];
+        let mut right_count = [0_usize; SAH_BINS];
+        let mut sweep: Option<AABB> = None;
+        let mut count = 0;
+        for bin in (1..SAH_BINS).rev() {
+            sweep = union(sweep, boxes[bin]);
+            count += counts[bin];
+            right_area[bin] = sweep.map_or(0.0, |b| b.surface_area());
+            right_count[bin] = count;
+        }
+
+        let mut sweep: Option<AABB> = None;
+        let mut count = 0;
+        for boundary in 1..SAH_BINS {
+            sweep = union(sweep, boxes[boundary - 1]);
+            count += counts[boundary - 1];
+            if count == 0 || right_count[boundary] == 0 {
+                continue;
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let cost = TRAVERSAL_COST
+                + (sweep.map_or(0.0, |b| b.surface_area()) * count as f64
+                    + right_area[boundary] * right_count[boundary] as f64)
+                    / parent_area;
+            let cheaper = match best {
+                Some(b) => cost < b.cost,
+                None => true,
+            };
+            if cheaper {
+                best = Some(BinSplit {
+                    boundary,
+                    cost,
+                    ..binned
+                });
+            }
+        }
+    }
+    best
+}
+
+// Testing every object, in the same units as a split's cost
+#[allow(clippy::cast_precision_loss)]
+fn leaf_cost(objects: &[Boxed]) -> f64 {
+    objects.len() as f64
+}
+
+fn union(a: Option<AABB>, b: Option<AABB>) -> Option<AABB> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.surrounding_box(&b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn surrounding<F: Fn(&Boxed) -> AABB>(objects: &[Boxed], box_of: F) -> AABB {
+    objects
+        .iter()
+        .map(box_of)
+        .fold(None, |bounds: Option<AABB>, b| {
+            Some(bounds.map_or(b, |s| s.surrounding_box(&b)))
+        })
+        .expect("BVH needs at least one object")
+}
+
+fn widest_axis(b: &AABB) -> u8 {
+    let size = b.max - b.min;
+    if size.x >= size.y && size.x >= size.z {
+        0
+    } else if size.y >= size.z {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FlatBVH, Split, BVH};
+    use crate::cuboid::Cuboid;
+    use crate::hit::Hittable;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+    use rand::Rng;
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    // A ground sphere under lots of small ones, like random_spheres
+    fn spheres() -> Vec<Arc<dyn Hittable>> {
+        let mut rng = Pcg64Mcg::new(5);
+        let mut objects: Vec<Arc<dyn Hittable>> = vec![Arc::new(Sphere {
+            center: Vec3::from_xyz(0.0, -1000.0, 0.0),
+            radius: 1000.0,
+            ..Sphere::default()
+        })];
+        for _ in 0..300 {
+            objects.push(Arc::new(Sphere {
+                center: Vec3::from_xyz(rng.gen_range(-10.0, 10.0), 0.2, rng.gen_range(-10.0, 10.0)),
+                radius: 0.2,
+                ..Sphere::default()
+            }));
+        }
+        objects
+    }
+
+    #[test]
+    fn same_hits() {
+        let random = BVH::new(&mut Pcg64Mcg::new(1), spheres(), 0.0, 1.0, Split::Random);
+        let sah = BVH::new(
+            &mut Pcg64Mcg::new(1),
+            spheres(),
+            0.0,
+            1.0,
+            Split::SurfaceArea,
+        );
+        assert_eq!(random.bounding_box(0.0, 1.0), sah.bounding_box(0.0, 1.0));
+
+        let mut rng = Pcg64Mcg::new(2);
+        for _ in 0..2000 {
+            let r = Ray {
+                origin: Vec3::from_xyz(
+                    rng.gen_range(-12.0, 12.0),
+                    rng.gen_range(0.1, 3.0),
+                    rng.gen_range(-12.0, 12.0),
+                ),
+                direction: Vec3::random_unit_vector(&mut rng),
+                time: 0.0,
+            };
+            assert_eq!(
+                random.hit(&r, 0.001, f64::INFINITY, &mut rng),
+                sah.hit(&r, 0.001, f64::INFINITY, &mut rng)
+            );
+        }
+    }
+
+    #[test]
+    fn flat() {
+        // the spheres with a few blocks between them, for nodes that hold different kinds of objects
+        let scene = || {
+            let mut objects = spheres();
+            let mut rng = Pcg64Mcg::new(6);
+            for _ in 0..20 {
+                let min =
+                    Vec3::from_xyz(rng.gen_range(-10.0, 10.0), 0.0, rng.gen_range(-10.0, 10.0));
+                objects.push(Arc::new(Cuboid::new(
+                    min,
+                    min + Vec3::random(&mut rng, 0.1, 1.5),
+                    Sphere::default().material,
+                )));
+            }
+            objects
+        };
+
+        let mut rng = Pcg64Mcg::new(3);
+        for &split in &[Split::Random, Split::SurfaceArea] {
+            let tree = BVH::new(&mut Pcg64Mcg::new(1), scene(), 0.0, 1.0, split);
+            let flat = FlatBVH::new(&mut Pcg64Mcg::new(1), scene(), 0.0, 1.0, split);
+            assert_eq!(tree.bounding_box(0.0, 1.0), flat.bounding_box(0.0, 1.0));
+
+            let mut hits = 0;
+            for _ in 0..5000 {
+                let r = Ray {
+                    origin: Vec3::from_xyz(
+                        rng.gen_range(-12.0, 12.0),
+                        rng.gen_range(0.1, 3.0),
+                        rng.gen_range(-12.0, 12.0),
+                    ),
+                    direction: Vec3::random_unit_vector(&mut rng),
+                    time: 0.0,
+                };
+                let hit = tree.hit(&r, 0.001, f64::INFINITY, &mut rng);
+                assert_eq!(hit, flat.hit(&r, 0.001, f64::INFINITY, &mut rng));
+                // and with the far end cut short
+                assert_eq!(
+                    tree.hit(&r, 0.001, 2.0, &mut rng),
+                    flat.hit(&r, 0.001, 2.0, &mut rng)
+                );
+                hits += usize::from(hit.is_some());
+            }
+            assert!(hits > 2000);
+        }
+
+        // a single object is still in a node
+        let one = FlatBVH::new(
+            &mut Pcg64Mcg::new(1),
+            spheres().split_off(300),
+            0.0,
+            1.0,
+            Split::Random,
+        );
+        assert_eq!(one.nodes.len(), 3);
+    }
+
+    #[test]
+    fn small() {
+        let mut rng = Pcg64Mcg::new(0);
+        // one object, and objects all in the same place
+        let one = BVH::new(
+            &mut Pcg64Mcg::new(1),
+            spheres().split_off(300),
+            0.0,
+            1.0,
+            Split::SurfaceArea,
+        );
+        let down = Ray {
+            origin: Vec3::from_xyz(0.0, 5.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(one.bounding_box(0.0, 1.0).is_some());
+
+        let stacked: Vec<Arc<dyn Hittable>> = (0..10)
+            .map(|_| {
+                Arc::new(Sphere {
+                    center: Vec3::new(),
+                    radius: 1.0,
+                    ..Sphere::default()
+                }) as Arc<dyn Hittable>
+            })
+            .collect();
+        let stacked = BVH::new(&mut Pcg64Mcg::new(1), stacked, 0.0, 1.0, Split::SurfaceArea);
+        assert!(
+            (stacked
+                .hit(&down, 0.001, f64::INFINITY, &mut rng)
+                .unwrap()
+                .t
+                - 4.0)
+                .abs()
+                < 1e-12
+        );
+    }
 }
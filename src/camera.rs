@@ -51,7 +51,7 @@ impl Camera {
         }
     }
 
-    pub fn ray(&mut self, mut rng: &mut Pcg64Mcg, s: f64, t: f64) -> Ray {
+    pub fn ray(&self, mut rng: &mut Pcg64Mcg, s: f64, t: f64) -> Ray {
         let rd = Vec3::random_in_unit_disk(&mut rng) * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
         Ray {
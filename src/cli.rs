@@ -0,0 +1,520 @@
+use super::bvh::Split;
+use super::tonemap::{Operator, ToneMap};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Command line options for a render
+
+pub const USAGE: &str = "Usage: raytracer [OPTIONS] [SCENE]
+
+SCENE is the name of a built in scene or the path of a scene file (same as --scene)
+
+Options:
+  -o, --output <PATH>     write the image to PATH instead of stdout
+  -W, --width <PIXELS>    image width [default: 1280]
+  -H, --height <PIXELS>   image height [default: width / aspect]
+  -a, --aspect <RATIO>    aspect ratio as W:H or a number, when only one dimension is given [default: 16:9]
+  -s, --samples <N>       samples per pixel [default: 50]
+  -d, --depth <N>         maximum bounces per ray [default: 50]
+  -t, --threads <N>       render threads [default: 8]
+      --bvh <SPLIT>       sah, or random to split on a random axis for timing comparisons [default: sah]
+      --sun <DEGREES>     light the scene with a daylight sky instead of its own background, the sun this high
+                          above the horizon
+      --turbidity <T>     haziness of the --sun sky, from 2 (clear) to 10 [default: 3]
+      --seed <N>          seed for the scene and samples, for reproducible renders [default: time based]
+      --scene <SCENE>     built in scene name or scene file [default: two_spheres]
+  -m, --tonemap <OP>      clamp, reinhard, reinhard_extended, or aces, for PPM and PNG [default: clamp]
+      --white <VALUE>     radiance that reinhard_extended maps to white [default: 4]
+  -e, --exposure <STOPS>  brighten, or darken when negative, before tone mapping [default: 0]
+  -f, --format <FORMAT>   p3, p6 (ppm), png, png16, hdr, exr (half float), or exr32
+                          [default: from the output's extension, or p6]
+  -h, --help              print this message";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    P3,
+    P6,
+    Png,
+    // 16 bits per channel
+    Png16,
+    // Radiance RGBE
+    Hdr,
+    // OpenEXR with half floats
+    Exr,
+    // OpenEXR with 32-bit floats
+    Exr32,
+}
+
+impl Format {
+    fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "ppm" => Some(Self::P6),
+            "png" => Some(Self::Png),
+            "hdr" => Some(Self::Hdr),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "p3" => Ok(Self::P3),
+            "p6" | "ppm" => Ok(Self::P6),
+            "png" => Ok(Self::Png),
+            "png16" => Ok(Self::Png16),
+            "hdr" => Ok(Self::Hdr),
+            "exr" => Ok(Self::Exr),
+            "exr32" => Ok(Self::Exr32),
+            _ => Err(String::from(
+                "expected p3, p6, ppm, png, png16, hdr, exr, or exr32",
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    // None writes to stdout
+    pub output: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u16,
+    pub threads: u32,
+    // how the BVH picks where to split objects
+    pub split: Split,
+    // Sun elevation in degrees for a daylight sky replacing the scene's background, None keeps the background
+    pub sun: Option<f64>,
+    pub turbidity: f64,
+    // None picks one from the clock
+    pub seed: Option<u128>,
+    // a built in scene's name or a scene file's path, decided by the caller
+    pub scene: Option<OsString>,
+    pub format: Format,
+    pub tone_map: ToneMap,
+}
+
+impl Options {
+    pub fn aspect_ratio(&self) -> f64 {
+        f64::from(self.width) / f64::from(self.height)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Render(Options),
+    Help,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue {
+        option: String,
+        value: String,
+        message: String,
+    },
+    // Options that parse fine on their own but can't be rendered together
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOption(o) => write!(f, "unknown option {}", o),
+            Self::MissingValue(o) => write!(f, "{} needs a value", o),
+            Self::InvalidValue {
+                option,
+                value,
+                message,
+            } => write!(f, "invalid value \"{}\" for {}: {}", value, option, message),
+            Self::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[allow(clippy::too_many_lines)]
+pub fn parse<I: IntoIterator<Item = OsString>>(args: I) -> Result<Command, Error> {
+    let mut output = None;
+    let mut width = None;
+    let mut height = None;
+    let mut aspect = None;
+    let mut samples_per_pixel = 50;
+    let mut max_depth = 50;
+    let mut threads = 8;
+    let mut split = Split::SurfaceArea;
+    let mut sun = None;
+    let mut turbidity = 3.0;
+    let mut seed = None;
+    let mut scene = None;
+    let mut format = None;
+    let mut operator = None;
+    let mut white = None;
+    let mut exposure = 0.0;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let name = match arg.to_str() {
+            Some(a) if a.starts_with('-') && a != "-" => a.to_owned(),
+            // anything that isn't an option is the scene
+            _ => {
+                set_scene(&mut scene, arg)?;
+                continue;
+            }
+        };
+
+        // accept --name=value as well as --name value
+        let (name, inline_value) = match name.find('=') {
+            Some(i) if name.starts_with("--") => {
+                (name[..i].to_owned(), Some(OsString::from(&name[i + 1..])))
+            }
+            _ => (name, None),
+        };
+        let mut value = || match inline_value.clone().or_else(|| args.next()) {
+            Some(v) => Ok(v),
+            None => Err(Error::MissingValue(name.clone())),
+        };
+
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => {
+                let v = value()?;
+                output = if v == "-" {
+                    None
+                } else {
+                    Some(PathBuf::from(v))
+                }
+            }
+            "-W" | "--width" => width = Some(parse_value(&name, &value()?)?),
+            "-H" | "--height" => height = Some(parse_value(&name, &value()?)?),
+            "-a" | "--aspect" => aspect = Some(parse_aspect(&name, &value()?)?),
+            "-s" | "--samples" => samples_per_pixel = parse_value(&name, &value()?)?,
+            "-d" | "--depth" => max_depth = parse_value(&name, &value()?)?,
+            "-t" | "--threads" => threads = parse_value(&name, &value()?)?,
+            "--bvh" => split = parse_value(&name, &value()?)?,
+            "--sun" => sun = Some(parse_range(&name, &value()?, 0.0, 90.0)?),
+            "--turbidity" => turbidity = parse_range(&name, &value()?, 2.0, 10.0)?,
+            "--seed" => seed = Some(parse_value(&name, &value()?)?),
+            "--scene" => set_scene(&mut scene, value()?)?,
+            "-f" | "--format" => format = Some(parse_value(&name, &value()?)?),
+            "-m" | "--tonemap" => operator = Some(value()?),
+            "--white" => white = Some(parse_value(&name, &value()?)?),
+            "-e" | "--exposure" => exposure = parse_value(&name, &value()?)?,
+            _ => return Err(Error::UnknownOption(name)),
+        }
+    }
+
+    let (width, height) = image_size(width, height, aspect)?;
+
+    if threads == 0 {
+        return Err(Error::Invalid(String::from("--threads must be at least 1")));
+    }
+    if samples_per_pixel == 0 {
+        return Err(Error::Invalid(String::from("--samples must be at least 1")));
+    }
+    if max_depth == 0 {
+        return Err(Error::Invalid(String::from("--depth must be at least 1")));
+    }
+
+    // an explicit format wins, otherwise the output's extension picks one
+    let format = match (format, &output) {
+        (Some(f), _) => f,
+        (None, None) => Format::P6,
+        (None, Some(path)) => match path.extension() {
+            None => Format::P6,
+            Some(ext) => Format::from_extension(path).ok_or_else(|| {
+                Error::Invalid(format!(
+                    "can't tell the image format from .{}, use --format",
+                    ext.to_string_lossy()
+                ))
+            })?,
+        },
+    };
+
+    let tone_map = ToneMap {
+        operator: tone_map_operator(operator.as_deref(), white)?,
+        exposure,
+    };
+
+    Ok(Command::Render(Options {
+        output,
+        width,
+        height,
+        samples_per_pixel,
+        max_depth,
+        threads,
+        split,
+        sun,
+        turbidity,
+        seed,
+        scene,
+        format,
+        tone_map,
+    }))
+}
+
+// Fills in whichever of width and height wasn't given from the aspect ratio
+fn image_size(
+    width: Option<u32>,
+    height: Option<u32>,
+    aspect: Option<f64>,
+) -> Result<(u32, u32), Error> {
+    let (width, height) = match (width, height, aspect) {
+        (Some(_), Some(_), Some(_)) => {
+            return Err(Error::Invalid(String::from(
+                "--aspect can't be used with both --width and --height",
+            )))
+        }
+        (Some(w), Some(h), None) => (w, h),
+        (w, h, a) => {
+            let ratio = a.unwrap_or(16.0 / 9.0);
+            #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+            match (w, h) {
+                (_, Some(h)) => ((f64::from(h) * ratio).round() as u32, h),
+                (w, _) => {
+                    let w = w.unwrap_or(1280);
+                    (w, (f64::from(w) / ratio).round() as u32)
+                }
+            }
+        }
+    };
+
+    // u and v are divided by width - 1 and height - 1 to get pixel positions
+    if width < 2 || height < 2 {
+        return Err(Error::Invalid(format!(
+            "image is {}x{}, it needs to be at least 2 pixels in each direction",
+            width, height
+        )));
+    }
+
+    Ok((width, height))
+}
+
+fn tone_map_operator(name: Option<&OsStr>, white: Option<f64>) -> Result<Operator, Error> {
+    if let Some(w) = white {
+        if name != Some(OsStr::new("reinhard_extended")) {
+            return Err(Error::Invalid(String::from(
+                "--white only applies to --tonemap reinhard_extended",
+            )));
+        }
+        if w.is_nan() || w <= 0.0 {
+            return Err(Error::Invalid(String::from("--white must be above 0")));
+        }
+    }
+
+    match name {
+        None => Ok(Operator::Clamp),
+        Some(n) => match n.to_str() {
+            Some("clamp") => Ok(Operator::Clamp),
+            Some("reinhard") => Ok(Operator::Reinhard),
+            Some("reinhard_extended") => Ok(Operator::ExtendedReinhard {
+                white: white.unwrap_or(4.0),
+            }),
+            Some("aces") => Ok(Operator::Aces),
+            _ => Err(Error::InvalidValue {
+                option: String::from("--tonemap"),
+                value: n.to_string_lossy().into_owned(),
+                message: String::from("expected clamp, reinhard, reinhard_extended, or aces"),
+            }),
+        },
+    }
+}
+
+fn set_scene(scene: &mut Option<OsString>, value: OsString) -> Result<(), Error> {
+    if let Some(s) = scene {
+        return Err(Error::Invalid(format!(
+            "more than one scene given: {} and {}",
+            s.to_string_lossy(),
+            value.to_string_lossy()
+        )));
+    }
+    *scene = Some(value);
+    Ok(())
+}
+
+fn parse_value<T: FromStr>(option: &str, value: &OsString) -> Result<T, Error>
+where
+    T::Err: fmt::Display,
+{
+    let invalid = |message: String| Error::InvalidValue {
+        option: option.to_owned(),
+        value: value.to_string_lossy().into_owned(),
+        message,
+    };
+    value
+        .to_str()
+        .ok_or_else(|| invalid(String::from("not valid unicode")))?
+        .parse()
+        .map_err(|e: T::Err| invalid(e.to_string()))
+}
+
+// A number from min to max inclusive
+fn parse_range(option: &str, value: &OsString, min: f64, max: f64) -> Result<f64, Error> {
+    let number: f64 = parse_value(option, value)?;
+    if number >= min && number <= max {
+        Ok(number)
+    } else {
+        Err(Error::InvalidValue {
+            option: option.to_owned(),
+            value: value.to_string_lossy().into_owned(),
+            message: format!("must be from {} to {}", min, max),
+        })
+    }
+}
+
+// 16:9 or 1.777
+fn parse_aspect(option: &str, value: &OsString) -> Result<f64, Error> {
+    let invalid = |message: &str| Error::InvalidValue {
+        option: option.to_owned(),
+        value: value.to_string_lossy().into_owned(),
+        message: message.to_owned(),
+    };
+    let s = value.to_str().ok_or_else(|| invalid("not valid unicode"))?;
+
+    let ratio = match s.find(':') {
+        Some(i) => {
+            let w: f64 = s[..i].parse().map_err(|_| invalid("expected W:H"))?;
+            let h: f64 = s[i + 1..].parse().map_err(|_| invalid("expected W:H"))?;
+            w / h
+        }
+        None => s.parse().map_err(|_| invalid("expected W:H or a number"))?,
+    };
+
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(invalid("must be positive"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Command, Error, Format, Operator, OsString, PathBuf, Split, ToneMap};
+
+    fn parse_str(args: &str) -> Result<Command, Error> {
+        parse(args.split_whitespace().map(OsString::from))
+    }
+
+    fn options(args: &str) -> super::Options {
+        match parse_str(args) {
+            Ok(Command::Render(o)) => o,
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let o = options("");
+        assert_eq!((o.width, o.height), (1280, 720));
+        assert_eq!(o.samples_per_pixel, 50);
+        assert_eq!(o.max_depth, 50);
+        assert_eq!(o.threads, 8);
+        assert_eq!(o.split, Split::SurfaceArea);
+        assert_eq!((o.sun, o.turbidity), (None, 3.0));
+        assert_eq!(o.output, None);
+        assert_eq!(o.seed, None);
+        assert_eq!(o.scene, None);
+        assert_eq!(o.format, Format::P6);
+        assert_eq!(o.tone_map, ToneMap::default());
+        assert_eq!(parse_str("-s 4 --help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn options_and_values() {
+        let o = options(
+            "-o out.ppm --width=640 -a 2:1 -s 12 -d 8 -t 4 --seed 7 cornell_box --format p3",
+        );
+        assert_eq!(o.output, Some(PathBuf::from("out.ppm")));
+        assert_eq!((o.width, o.height), (640, 320));
+        assert_eq!((o.samples_per_pixel, o.max_depth, o.threads), (12, 8, 4));
+        assert_eq!(o.seed, Some(7));
+        assert_eq!(o.scene, Some(OsString::from("cornell_box")));
+        assert_eq!(o.format, Format::P3);
+
+        let o = options("--tonemap reinhard_extended --white 8 -e -1.5");
+        assert_eq!(
+            o.tone_map,
+            ToneMap {
+                operator: Operator::ExtendedReinhard { white: 8.0 },
+                exposure: -1.5
+            }
+        );
+        assert_eq!(options("-m aces").tone_map.operator, Operator::Aces);
+        assert_eq!(options("--bvh random").split, Split::Random);
+        let o = options("--sun 15 --turbidity 6.5");
+        assert_eq!((o.sun, o.turbidity), (Some(15.0), 6.5));
+
+        assert_eq!(options("-o out.PNG").format, Format::Png);
+        assert_eq!(options("-o out.png -f png16").format, Format::Png16);
+        assert_eq!(options("-o out").format, Format::P6);
+        assert_eq!(options("-o out.hdr").format, Format::Hdr);
+        assert_eq!(options("-o out.exr -f exr32").format, Format::Exr32);
+
+        let o = options("-H 100 --aspect 1.5 -o -");
+        assert_eq!((o.width, o.height), (150, 100));
+        assert_eq!(o.output, None);
+        assert!((o.aspect_ratio() - 1.5).abs() < 1e-12);
+
+        assert!((options("-W 300 -H 200").aspect_ratio() - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn errors() {
+        let is_invalid = |args: &str| matches!(parse_str(args), Err(Error::Invalid(_)));
+        assert!(is_invalid("-W 3 -a 4"));
+        assert!(is_invalid("-W 10 -H 0"));
+        assert!(is_invalid("-s 0"));
+        assert!(is_invalid("-t 0"));
+        assert!(is_invalid("-W 10 -H 10 -a 1"));
+        assert!(is_invalid("a.scene b.scene"));
+        assert!(is_invalid("-o out.jpg"));
+        assert!(is_invalid("-m aces --white 2"));
+        assert!(is_invalid("-m reinhard_extended --white 0"));
+
+        assert_eq!(
+            parse_str("--frobnicate"),
+            Err(Error::UnknownOption(String::from("--frobnicate")))
+        );
+        assert_eq!(
+            parse_str("-s"),
+            Err(Error::MissingValue(String::from("-s")))
+        );
+        assert!(matches!(
+            parse_str("-W wide"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_str("-a 0:1"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_str("-m filmic"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_str("--bvh median"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_str("--sun -5"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_str("--sun 30 --turbidity 12"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_str("-f gif"),
+            Err(Error::InvalidValue { .. })
+        ));
+    }
+}
@@ -0,0 +1,49 @@
+use super::tonemap::ToneMap;
+use super::vec3::Vec3;
+
+// Turning a pixel's summed samples into the 0 to 1 display values that every image writer quantizes
+
+// Linear radiance of the pixel, for writers that keep values above 1
+pub fn average(color: &Vec3, samples_per_pixel: u32) -> Vec3 {
+    if !color.is_valid_radiance() {
+        panic!("Color {} {} out of range", &color, samples_per_pixel)
+    }
+
+    *color / f64::from(samples_per_pixel)
+}
+
+// Averages the samples and tone maps them for writers with a fixed range
+pub fn display(color: &Vec3, samples_per_pixel: u32, tone_map: &ToneMap) -> Vec3 {
+    tone_map.apply(average(color, samples_per_pixel))
+}
+
+// Each of the 256 levels covers an equal slice of 0 to 1, with 1 itself in the top one
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+pub fn to_u8(c: f64) -> u8 {
+    (256.0 * c).min(255.0) as u8
+}
+
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+pub fn to_u16(c: f64) -> u16 {
+    (65536.0 * c).min(65535.0) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::{display, to_u16, to_u8, ToneMap, Vec3};
+
+    #[test]
+    fn display_values() {
+        let c = display(&Vec3::from_xyz(4.0, 0.0, 16.0), 4, &ToneMap::default());
+        assert_eq!(c, Vec3::from_xyz(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn quantize() {
+        assert_eq!(to_u8(0.0), 0);
+        assert_eq!(to_u8(0.5), 128);
+        assert_eq!(to_u8(1.0), 255);
+        assert_eq!(to_u16(0.5), 32768);
+        assert_eq!(to_u16(1.0), 65535);
+    }
+}
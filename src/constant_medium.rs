@@ -0,0 +1,154 @@
+use super::aabb::AABB;
+use super::hit::{Hit, Hittable};
+use super::material::{Isotropic, MaterialWritable};
+use super::ray::Ray;
+use super::texture::TextureWritable;
+use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// Smoke or fog of the same density throughout a boundary, rays pass through it or scatter at a random point inside.
+// The boundary has to be convex, a ray leaving it is assumed never to re-enter it.
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Hittable>,
+    // Negative reciprocal of the density, the mean free path scaled for sampling distances
+    neg_inv_density: f64,
+    // Decides the direction and color of scattered light, usually isotropic
+    pub phase_function: Arc<dyn MaterialWritable>,
+}
+
+impl ConstantMedium {
+    pub fn new(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn MaterialWritable>,
+    ) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+
+    // A medium scattering equally in every direction, tinted by albedo
+    pub fn isotropic(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        albedo: Box<dyn TextureWritable>,
+    ) -> Self {
+        Self::new(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        // where the whole line enters and leaves the boundary, so rays starting inside it still see the medium
+        let enter = self
+            .boundary
+            .hit(r, f64::NEG_INFINITY, f64::INFINITY, rng)?;
+        let leave = self.boundary.hit(r, enter.t + 0.0001, f64::INFINITY, rng)?;
+
+        let t0 = enter.t.max(t_min).max(0.0);
+        let t1 = leave.t.min(t_max);
+        if t0 >= t1 {
+            return None;
+        }
+
+        // distance to scattering is exponentially distributed, light makes it through if that's past the far side
+        let ray_length = r.direction.length();
+        // 1 - gen is in (0, 1], so the log is finite
+        let distance = self.neg_inv_density * (1.0 - rng.gen::<f64>()).ln();
+        if distance > (t1 - t0) * ray_length {
+            return None;
+        }
+
+        let t = t0 + distance / ray_length;
+        Some(Hit {
+            point: r.at(t),
+            // the phase function doesn't use a surface normal, any will do
+            normal: Vec3::from_xyz(1.0, 0.0, 0.0),
+            t,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            color: None,
+            material: Arc::clone(&self.phase_function),
+        })
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConstantMedium, Hittable, Pcg64Mcg, Ray, Vec3};
+    use crate::sphere::Sphere;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    fn fog(density: f64) -> ConstantMedium {
+        ConstantMedium::isotropic(
+            Arc::new(Sphere {
+                center: Vec3::new(),
+                radius: 1.0,
+                ..Sphere::default()
+            }),
+            density,
+            Box::new(SolidColor::from_rgb(0.5, 0.5, 0.5)),
+        )
+    }
+
+    fn ray(origin: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: Vec3::from_xyz(0.0, 0.0, 1.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn medium() {
+        let mut rng = Pcg64Mcg::new(1);
+        let outside = Vec3::from_xyz(0.0, 0.0, -5.0);
+        // light crossing 2 units of a medium with density 0.5 makes it through e^-1 of the time
+        let thin = fog(0.5);
+        let mut distances = vec![];
+        let mut through = 0;
+        for _ in 0..10_000 {
+            match thin.hit(&ray(outside), 0.001, f64::INFINITY, &mut rng) {
+                Some(hit) => distances.push(hit.t),
+                None => through += 1,
+            }
+        }
+        assert!((f64::from(through) / 10_000.0 - (-1.0_f64).exp()).abs() < 0.02);
+        let scattered = distances.len();
+        // the same ray scatters at a fresh distance every time
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances.dedup();
+        assert_eq!(distances.len(), scattered);
+
+        // a dense medium scatters right after entering, including for rays starting inside it
+        let dense = fog(1000.0);
+        let hit = dense
+            .hit(&ray(outside), 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!(hit.t > 4.0 && hit.t < 4.05);
+        let hit = dense
+            .hit(&ray(Vec3::new()), 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!(hit.t < 0.05);
+        assert!(format!("{:?}", hit.material).starts_with("Isotropic"));
+
+        // nothing when the ray misses, or the medium is past t_max
+        let miss = Ray {
+            origin: outside,
+            direction: Vec3::from_xyz(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(dense.hit(&miss, 0.001, f64::INFINITY, &mut rng).is_none());
+        assert!(dense.hit(&ray(outside), 0.001, 3.0, &mut rng).is_none());
+    }
+}
@@ -0,0 +1,133 @@
+use super::aabb::AABB;
+use super::hit::{FlipFace, Hit, Hittable};
+use super::material::MaterialWritable;
+use super::ray::Ray;
+use super::rect::{XYRect, XZRect, YZRect};
+use super::vec3::Vec3;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// Axis aligned box made of six rectangles, the ones on the min sides flipped so every face points outward
+pub struct Cuboid {
+    min: Vec3,
+    max: Vec3,
+    sides: Vec<Box<dyn Hittable>>,
+}
+
+impl Cuboid {
+    pub fn new(min: Vec3, max: Vec3, material: Arc<dyn MaterialWritable>) -> Self {
+        let sides: Vec<Box<dyn Hittable>> = vec![
+            Box::new(XYRect {
+                x0: min.x,
+                x1: max.x,
+                y0: min.y,
+                y1: max.y,
+                k: max.z,
+                material: Arc::clone(&material),
+            }),
+            Box::new(FlipFace {
+                object: XYRect {
+                    x0: min.x,
+                    x1: max.x,
+                    y0: min.y,
+                    y1: max.y,
+                    k: min.z,
+                    material: Arc::clone(&material),
+                },
+            }),
+            Box::new(XZRect {
+                x0: min.x,
+                x1: max.x,
+                z0: min.z,
+                z1: max.z,
+                k: max.y,
+                material: Arc::clone(&material),
+            }),
+            Box::new(FlipFace {
+                object: XZRect {
+                    x0: min.x,
+                    x1: max.x,
+                    z0: min.z,
+                    z1: max.z,
+                    k: min.y,
+                    material: Arc::clone(&material),
+                },
+            }),
+            Box::new(YZRect {
+                y0: min.y,
+                y1: max.y,
+                z0: min.z,
+                z1: max.z,
+                k: max.x,
+                material: Arc::clone(&material),
+            }),
+            Box::new(FlipFace {
+                object: YZRect {
+                    y0: min.y,
+                    y1: max.y,
+                    z0: min.z,
+                    z1: max.z,
+                    k: min.x,
+                    material,
+                },
+            }),
+        ];
+
+        Self { min, max, sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        self.sides.hit(r, t_min, t_max, rng)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<AABB> {
+        Some(AABB {
+            min: self.min,
+            max: self.max,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Arc, Cuboid, Hittable, Ray, Vec3};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
+        let c = Cuboid::new(
+            Vec3::from_xyz(-1.0, -1.0, 2.0),
+            Vec3::from_xyz(1.0, 1.0, 4.0),
+            Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        );
+        let front_ray = Ray {
+            origin: Vec3::from_xyz(0.0, 0.5, 0.0),
+            direction: Vec3::from_xyz(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let inside_ray = Ray {
+            origin: Vec3::from_xyz(0.0, 0.0, 3.0),
+            direction: Vec3::from_xyz(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        let front_hit = c.hit(&front_ray, 0.0, 10.0, &mut rng).unwrap();
+        assert_eq!(front_hit.point, Vec3::from_xyz(0.0, 0.5, 2.0));
+        assert_eq!(front_hit.normal, Vec3::from_xyz(0.0, 0.0, -1.0));
+        assert!(front_hit.front_face);
+
+        let inside_hit = c.hit(&inside_ray, 0.0, 10.0, &mut rng).unwrap();
+        assert_eq!(inside_hit.point, Vec3::from_xyz(1.0, 0.0, 3.0));
+        assert_eq!(inside_hit.normal, Vec3::from_xyz(-1.0, 0.0, 0.0));
+        assert!(!inside_hit.front_face);
+
+        assert!(c.hit(&front_ray, 0.0, 1.0, &mut rng).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use super::environment::{Environment, EnvironmentWritable};
+use super::onb::ONB;
+use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::f64::consts::PI;
+
+// Clear sky daylight from the Preetham model ("A Practical Analytic Model for Daylight", 1999), which fits the
+// sky's luminance and chromaticity to the sun's height and the air's turbidity, along with the sun itself and
+// ground lit by both below the horizon
+
+// Render radiance for each kcd/m2 of luminance, which puts sunlit white ground around 1 in the middle of the day
+const RADIANCE_PER_KCD: f64 = 0.06;
+// Angle from the middle of the sun's disk to its edge
+const SUN_RADIUS: f64 = 0.004_65;
+// Luminance of the sun above the atmosphere in kcd/m2
+const SUN_LUMINANCE: f64 = 2.0e6;
+// Wavelengths in micrometers standing in for the red, green, and blue channels when attenuating sunlight
+const WAVELENGTHS: [f64; 3] = [0.65, 0.55, 0.45];
+// Grid the sky is summed over for the light reaching the ground, in angles from the zenith and around it
+const GROUND_STEPS: (u32, u32) = (32, 64);
+
+#[derive(Debug)]
+pub struct Daylight {
+    // Unit direction towards the sun
+    sun: Vec3,
+    // Perez distribution coefficients A to E for luminance Y and chromaticities x and y
+    perez: [[f64; 5]; 3],
+    // Y, x, and y at the zenith over the Perez function there, which scales the function everywhere else
+    zenith: [f64; 3],
+    sun_radiance: Vec3,
+    cos_sun_radius: f64,
+    // Radiance of the ground, which reflects the sun and sky evenly
+    ground: Vec3,
+    intensity: f64,
+}
+
+impl Daylight {
+    // None if the sun is below the horizon, or sun has no length. Turbidity is how hazy the air is, from 2 for a
+    // very clear sky to 10 for a hazy one, and ground_albedo is the color of the ground beyond the scene.
+    #[allow(clippy::similar_names)]
+    pub fn new(sun: Vec3, turbidity: f64, ground_albedo: Vec3, intensity: f64) -> Option<Self> {
+        if sun.length_squared() == 0.0 || sun.y < 0.0 {
+            return None;
+        }
+        let sun = sun.unit_vector();
+        let t = turbidity;
+        let theta_s = sun.y.acos();
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic = |c: [f64; 4]| ((c[0] * theta_s + c[1]) * theta_s + c[2]) * theta_s + c[3];
+        let zenith_chromaticity =
+            |c: [[f64; 4]; 3]| t * t * cubic(c[0]) + t * cubic(c[1]) + cubic(c[2]);
+        let zenith_x = zenith_chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_yc = zenith_chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let mut zenith = [zenith_y.max(0.0), zenith_x, zenith_yc];
+        for (value, coefficients) in zenith.iter_mut().zip(&perez) {
+            *value /= perez_function(coefficients, 1.0, sun.y);
+        }
+
+        // sunlight loses blue to Rayleigh scattering and everything to haze, more through the longer path of air
+        // when it's low (Kasten's relative optical mass)
+        let mass = 1.0 / (sun.y + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.046_08 * t - 0.045_86;
+        let transmittance = |wavelength: f64| {
+            (-0.008_735 * wavelength.powf(-4.08) * mass).exp()
+                * (-beta * wavelength.powf(-1.3) * mass).exp()
+        };
+        let sun_radiance = Vec3::from_xyz(
+            transmittance(WAVELENGTHS[0]),
+            transmittance(WAVELENGTHS[1]),
+            transmittance(WAVELENGTHS[2]),
+        ) * SUN_LUMINANCE
+            * RADIANCE_PER_KCD;
+
+        let mut daylight = Self {
+            sun,
+            perez,
+            zenith,
+            sun_radiance,
+            cos_sun_radius: SUN_RADIUS.cos(),
+            ground: Vec3::new(),
+            intensity,
+        };
+        daylight.ground = ground_albedo * daylight.irradiance() / PI;
+        Some(daylight)
+    }
+
+    fn sun_solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.cos_sun_radius)
+    }
+
+    // The sky without the sun or intensity, for directions above the horizon
+    fn sky(&self, direction: Vec3) -> Vec3 {
+        let cos_theta = direction.y.max(1e-3);
+        let cos_gamma = direction.dot(self.sun).clamp(-1.0, 1.0);
+        let mut values = self.zenith;
+        for (value, coefficients) in values.iter_mut().zip(&self.perez) {
+            *value *= perez_function(coefficients, cos_theta, cos_gamma);
+        }
+        let [luminance, x, y] = values;
+        xyy_to_rgb(luminance, x, y) * RADIANCE_PER_KCD
+    }
+
+    // Light falling on flat ground from the sun and the whole sky
+    fn irradiance(&self) -> Vec3 {
+        let (theta_steps, phi_steps) = GROUND_STEPS;
+        let d_theta = PI / 2.0 / f64::from(theta_steps);
+        let d_phi = 2.0 * PI / f64::from(phi_steps);
+        let mut sky = Vec3::new();
+        for i in 0..theta_steps {
+            let (sin_theta, cos_theta) = ((f64::from(i) + 0.5) * d_theta).sin_cos();
+            for j in 0..phi_steps {
+                let (sin_phi, cos_phi) = ((f64::from(j) + 0.5) * d_phi).sin_cos();
+                let direction = Vec3::from_xyz(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+                sky += self.sky(direction) * (cos_theta * sin_theta * d_theta * d_phi);
+            }
+        }
+        sky + self.sun_radiance * (self.sun_solid_angle() * self.sun.y)
+    }
+}
+
+impl Environment for Daylight {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.unit_vector();
+        if direction.y < 0.0 {
+            return self.ground * self.intensity;
+        }
+        let mut color = self.sky(direction);
+        if direction.dot(self.sun) >= self.cos_sun_radius {
+            color += self.sun_radiance;
+        }
+        color * self.intensity
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.intensity > 0.0
+    }
+
+    // Only the sun is sampled, the rest of the sky is smooth enough to be found by scattered rays
+    fn random_direction(&self, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        let z = rng.gen::<f64>().mul_add(self.cos_sun_radius - 1.0, 1.0);
+        let (sin_phi, cos_phi) = (2.0 * PI * rng.gen::<f64>()).sin_cos();
+        let sin_theta = z.mul_add(-z, 1.0).sqrt();
+        Some(ONB::from_w(self.sun).local(cos_phi * sin_theta, sin_phi * sin_theta, z))
+    }
+
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        if direction.unit_vector().dot(self.sun) >= self.cos_sun_radius {
+            1.0 / self.sun_solid_angle()
+        } else {
+            0.0
+        }
+    }
+}
+
+impl EnvironmentWritable for Daylight {}
+
+// Perez et al.'s sky distribution, relative brightness by the angle from the zenith and from the sun
+#[allow(clippy::many_single_char_names)]
+fn perez_function(coefficients: &[f64; 5], cos_theta: f64, cos_gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    let gamma = cos_gamma.acos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+// Luminance and chromaticity to linear Rec. 709 (sRGB) primaries
+fn xyy_to_rgb(luminance: f64, x: f64, y: f64) -> Vec3 {
+    if y <= 0.0 {
+        return Vec3::new();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Vec3::from_xyz(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Daylight, Environment, Vec3, PI};
+    use rand_pcg::Pcg64Mcg;
+
+    fn sky(elevation: f64) -> Daylight {
+        let (sin, cos) = elevation.to_radians().sin_cos();
+        Daylight::new(
+            Vec3::from_xyz(0.0, sin, -cos),
+            3.0,
+            Vec3::from_xyz(0.3, 0.3, 0.3),
+            1.0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sky_colors() {
+        let noon = sky(70.0);
+        let up = noon.color(Vec3::from_xyz(0.0, 1.0, 0.0));
+        // blue overhead, and brighter towards the sun than away from it
+        assert!(up.b() > up.g() && up.g() > up.r());
+        let towards = noon.color(Vec3::from_xyz(0.0, 0.5, -1.0));
+        let away = noon.color(Vec3::from_xyz(0.0, 0.5, 1.0));
+        assert!(towards.g() > away.g());
+
+        // the sun is far brighter than the sky, and redder and dimmer when it's low
+        let sun = noon.color(Vec3::from_xyz(
+            0.0,
+            70_f64.to_radians().sin(),
+            -70_f64.to_radians().cos(),
+        ));
+        assert!(sun.g() > 1000.0 * up.g());
+        let low = sky(3.0);
+        let low_sun = low.color(low.sun);
+        assert!(low_sun.r() > low_sun.b() && low_sun.g() < sun.g());
+
+        // the ground reflects what lights it, and is darker in the evening
+        let ground = noon.color(Vec3::from_xyz(0.3, -1.0, 0.0));
+        assert!(ground.g() > 0.0);
+        assert!(low.color(Vec3::from_xyz(0.3, -1.0, 0.0)).g() < ground.g());
+
+        assert!(Daylight::new(Vec3::from_xyz(0.0, -1.0, 0.0), 3.0, Vec3::new(), 1.0).is_none());
+        assert!(Daylight::new(Vec3::new(), 3.0, Vec3::new(), 1.0).is_none());
+    }
+
+    #[test]
+    fn sun_sampling() {
+        let daylight = sky(40.0);
+        assert!(daylight.is_sampled());
+        let mut rng = Pcg64Mcg::new(1);
+        for _ in 0..100 {
+            let d = daylight.random_direction(&mut rng).unwrap();
+            assert!((d.length() - 1.0).abs() < 1e-9);
+            assert!(daylight.pdf_value(d) > 0.0);
+            // every direction picked sees the sun
+            assert!(daylight.color(d).g() > 1000.0);
+        }
+        // spread evenly over the sun's disk, which is tiny
+        let solid_angle = daylight.sun_solid_angle();
+        assert!((daylight.pdf_value(daylight.sun) * solid_angle - 1.0).abs() < 1e-9);
+        assert!((solid_angle - PI * 0.004_65_f64.powi(2)).abs() < 1e-9);
+        assert!(daylight.pdf_value(Vec3::from_xyz(0.0, 1.0, 0.0)) < f64::EPSILON);
+    }
+}
@@ -0,0 +1,294 @@
+use super::image::Image;
+use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::f64::consts::PI;
+use std::fmt;
+
+// Light arriving from infinitely far away along rays that escape the scene without hitting anything
+pub trait Environment {
+    // Light from direction, which needn't be a unit vector
+    fn color(&self, direction: Vec3) -> Vec3;
+
+    // Whether random_direction picks directions, so the environment is sampled like the scene's lights
+    fn is_sampled(&self) -> bool {
+        false
+    }
+
+    // A direction towards the environment, picked with density pdf_value
+    fn random_direction(&self, _rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        None
+    }
+
+    // Density over solid angle of random_direction picking direction
+    fn pdf_value(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub trait EnvironmentWritable: Environment + fmt::Debug + Send + Sync {}
+
+// White to blue by ray height
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gradient;
+
+impl Environment for Gradient {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let t = 0.5 * (direction.unit_vector().y + 1.0);
+        Vec3::from_xyz(1.0, 1.0, 1.0) * (1.0 - t) + Vec3::from_xyz(0.5, 0.7, 1.0) * t
+    }
+}
+
+impl EnvironmentWritable for Gradient {}
+
+// Single color, black for scenes lit only by emissive materials
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Solid(pub Vec3);
+
+impl Environment for Solid {
+    fn color(&self, _direction: Vec3) -> Vec3 {
+        self.0
+    }
+}
+
+impl EnvironmentWritable for Solid {}
+
+// Latitude-longitude (equirectangular) image around the scene: x across the image is the angle about the y axis,
+// with the middle column towards -z, and y down it is the angle from straight up to straight down. Directions
+// are picked by the brightness of each pixel and the solid angle it covers, so small bright areas like the sun
+// are found as easily as lights.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    image: Image,
+    // Sine and cosine of the angle the map is turned about the y axis
+    rotation: (f64, f64),
+    intensity: f64,
+    // Cumulative distribution for picking a row, then one for picking a pixel in each row
+    rows: Vec<f64>,
+    columns: Vec<f64>,
+    sampled: bool,
+}
+
+impl EnvironmentMap {
+    // Turned by rotation degrees anticlockwise seen from above, and brightened by intensity
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(image: Image, rotation: f64, intensity: f64) -> Self {
+        let (width, height) = (image.width, image.height);
+        let mut rows = Vec::with_capacity(height + 1);
+        let mut columns = Vec::with_capacity(height * (width + 1));
+        rows.push(0.0);
+        for y in 0..height {
+            // rows near the poles cover less of the sphere
+            let sin_theta = ((y as f64 + 0.5) / height as f64 * PI).sin();
+            let start = columns.len();
+            let mut total = 0.0;
+            columns.push(total);
+            for x in 0..width {
+                total += luminance(image.pixel(x, y)).max(0.0) * sin_theta;
+                columns.push(total);
+            }
+            normalize(&mut columns[start..]);
+            rows.push(rows[y] + total);
+        }
+        let sampled = rows[height] > 0.0 && intensity > 0.0;
+        normalize(&mut rows);
+
+        Self {
+            image,
+            rotation: rotation.to_radians().sin_cos(),
+            intensity,
+            rows,
+            columns,
+            sampled,
+        }
+    }
+
+    // The pixel seen in direction, and the sine of its angle from straight up
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn pixel(&self, direction: Vec3) -> (usize, usize, f64) {
+        // turned back into the map's own frame
+        let (sin, cos) = self.rotation;
+        let unit = direction.unit_vector();
+        let (x, z) = (cos * unit.x - sin * unit.z, sin * unit.x + cos * unit.z);
+
+        let theta = unit.y.clamp(-1.0, 1.0).acos();
+        let u = x.atan2(-z) / (2.0 * PI) + 0.5;
+        let v = theta / PI;
+        (
+            ((u * self.image.width as f64) as usize).min(self.image.width - 1),
+            ((v * self.image.height as f64) as usize).min(self.image.height - 1),
+            theta.sin(),
+        )
+    }
+
+    // Chance of picking the pixel, out of the whole map
+    fn pixel_chance(&self, x: usize, y: usize) -> f64 {
+        let row = &self.columns[y * (self.image.width + 1)..];
+        (self.rows[y + 1] - self.rows[y]) * (row[x + 1] - row[x])
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let (x, y, _) = self.pixel(direction);
+        self.image.pixel(x, y) * self.intensity
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    // A pixel picked by its chance, then a point picked evenly across it
+    #[allow(clippy::cast_precision_loss)]
+    fn random_direction(&self, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        let row = pick(&self.rows, rng.gen());
+        let start = row * (self.image.width + 1);
+        let column = pick(&self.columns[start..=start + self.image.width], rng.gen());
+        let u = (column as f64 + rng.gen::<f64>()) / self.image.width as f64;
+        let v = (row as f64 + rng.gen::<f64>()) / self.image.height as f64;
+
+        let (sin_phi, cos_phi) = ((u - 0.5) * 2.0 * PI).sin_cos();
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        let (x, z) = (sin_theta * sin_phi, -sin_theta * cos_phi);
+        // out of the map's frame into the scene's
+        let (sin, cos) = self.rotation;
+        Some(Vec3::from_xyz(
+            cos * x + sin * z,
+            cos_theta,
+            cos * z - sin * x,
+        ))
+    }
+
+    // The pixel's chance spread over the solid angle it covers: each pixel is (2 pi / width) by (pi / height)
+    // in angles, and a patch of those angles covers sin(theta) times as much solid angle
+    #[allow(clippy::cast_precision_loss)]
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        let (x, y, sin_theta) = self.pixel(direction);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let pixels = (self.image.width * self.image.height) as f64;
+        self.pixel_chance(x, y) * pixels / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl EnvironmentWritable for EnvironmentMap {}
+
+// Relative brightness of a linear Rec. 709 color to the eye
+fn luminance(c: Vec3) -> f64 {
+    0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b()
+}
+
+// Scales running totals so they end at 1, or makes them even when there's nothing to pick by
+#[allow(clippy::cast_precision_loss)]
+fn normalize(cdf: &mut [f64]) {
+    let last = cdf.len() - 1;
+    let total = cdf[last];
+    for (i, c) in cdf.iter_mut().enumerate() {
+        *c = if total > 0.0 {
+            *c / total
+        } else {
+            i as f64 / last as f64
+        };
+    }
+    cdf[last] = 1.0;
+}
+
+// The index i where cdf[i] <= xi < cdf[i + 1], which is never one with no chance of being picked
+fn pick(cdf: &[f64], xi: f64) -> usize {
+    let (mut low, mut high) = (0, cdf.len() - 1);
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+        if cdf[middle] <= xi {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pick, Environment, EnvironmentMap, Gradient, Solid, Vec3, PI};
+    use crate::image::Image;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn backgrounds() {
+        let up = Vec3::from_xyz(0.0, 2.0, 0.0);
+        assert_eq!(Gradient.color(up), Vec3::from_xyz(0.5, 0.7, 1.0));
+        assert_eq!(Solid(Vec3::new()).color(up), Vec3::new());
+        assert!(!Gradient.is_sampled());
+    }
+
+    #[test]
+    fn cdf() {
+        let cdf = [0.0, 0.25, 0.25, 1.0];
+        assert_eq!(pick(&cdf, 0.0), 0);
+        assert_eq!(pick(&cdf, 0.2), 0);
+        // the empty middle bin is skipped
+        assert_eq!(pick(&cdf, 0.25), 2);
+        assert_eq!(pick(&cdf, 0.999), 2);
+    }
+
+    #[test]
+    fn map() {
+        // dark but for one bright pixel, a quarter of the way across and below the horizon
+        let (width, height) = (16, 8);
+        let mut pixels = vec![Vec3::from_xyz(0.1, 0.1, 0.1); width * height];
+        pixels[5 * width + 4] = Vec3::from_xyz(1000.0, 500.0, 200.0);
+        let image = Image {
+            width,
+            height,
+            pixels,
+        };
+
+        let map = EnvironmentMap::new(image.clone(), 0.0, 2.0);
+        assert!(map.is_sampled());
+        // straight ahead is the middle of the map, and the left edge is behind
+        assert_eq!(map.pixel(Vec3::from_xyz(0.0, 0.1, -1.0)).0, 8);
+        assert_eq!(map.pixel(Vec3::from_xyz(0.0, 0.1, 1.0)).0, 15);
+        assert_eq!(map.pixel(Vec3::from_xyz(-1.0, 0.0, -0.1)).0, 4);
+        assert_eq!(map.pixel(Vec3::from_xyz(0.0, -1.0, 0.0)).1, 7);
+
+        // picked directions land in the pixels they were picked for, mostly the bright one
+        let mut rng = Pcg64Mcg::new(1);
+        let bright = (0..1000)
+            .filter_map(|_| map.random_direction(&mut rng))
+            .filter(|&d| map.color(d) == Vec3::from_xyz(2000.0, 1000.0, 400.0))
+            .count();
+        assert!(bright > 950);
+
+        // the density integrates to 1 over the sphere, checked with evenly spread directions
+        let n = 200;
+        let mut total = 0.0;
+        for i in 0..n {
+            let cos_theta = 1.0 - 2.0 * (f64::from(i) + 0.5) / f64::from(n);
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            for j in 0..2 * n {
+                let (sin_phi, cos_phi) = (PI * (f64::from(j) + 0.5) / f64::from(n)).sin_cos();
+                let d = Vec3::from_xyz(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+                total += map.pdf_value(d);
+            }
+        }
+        let integral = total * 4.0 * PI / f64::from(2 * n * n);
+        assert!((integral - 1.0).abs() < 0.02);
+
+        // a quarter turn anticlockwise seen from above takes what was on the left round to behind
+        let turned = EnvironmentMap::new(image, 90.0, 1.0);
+        let behind = Vec3::from_xyz(-0.1, -0.8, 1.0);
+        assert_eq!(turned.color(behind), Vec3::from_xyz(1000.0, 500.0, 200.0));
+        let d = turned.random_direction(&mut Pcg64Mcg::new(2)).unwrap();
+        assert!(turned.pdf_value(d) > 0.0);
+        let (x, y, _) = turned.pixel(d);
+        let (map_x, map_y, _) = map.pixel(Vec3::from_xyz(-d.z, d.y, d.x));
+        assert_eq!((x, y), (map_x, map_y));
+    }
+}
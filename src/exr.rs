@@ -0,0 +1,247 @@
+use super::color;
+use super::vec3::Vec3;
+
+// Uncompressed scanline OpenEXR images of linear radiance, everything in the file is little endian
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelType {
+    // 16-bit floats, what most compositors expect
+    Half,
+    Float,
+}
+
+impl PixelType {
+    // Values used for the type in the channel list
+    fn id(self) -> i32 {
+        match self {
+            Self::Half => 1,
+            Self::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Half => 2,
+            Self::Float => 4,
+        }
+    }
+}
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+// Version 2, single part scanline file
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+
+pub fn image(
+    width: u32,
+    height: u32,
+    colors: &[Vec3],
+    samples_per_pixel: u32,
+    pixel_type: PixelType,
+) -> Vec<u8> {
+    assert_eq!(
+        colors.len(),
+        width as usize * height as usize,
+        "{}x{} image needs a color for every pixel",
+        width,
+        height
+    );
+
+    let mut image = Vec::from(&MAGIC[..]);
+    image.extend_from_slice(&VERSION);
+
+    // channels are listed, and stored in each scanline, in alphabetical order
+    let mut channels = vec![];
+    for name in &[b"B", b"G", b"R"] {
+        channels.extend_from_slice(&name[..]);
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.id().to_le_bytes());
+        // not perceptually linear, three reserved bytes, then x and y sampling
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&1_i32.to_le_bytes());
+        channels.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    channels.push(0);
+
+    #[allow(clippy::cast_possible_wrap)]
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v: &i32| v.to_le_bytes().to_vec())
+        .collect();
+
+    write_attribute(&mut image, "channels", "chlist", &channels);
+    // no compression
+    write_attribute(&mut image, "compression", "compression", &[0]);
+    write_attribute(&mut image, "dataWindow", "box2i", &window);
+    write_attribute(&mut image, "displayWindow", "box2i", &window);
+    // increasing y, top row first
+    write_attribute(&mut image, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut image,
+        "pixelAspectRatio",
+        "float",
+        &1_f32.to_le_bytes(),
+    );
+    write_attribute(&mut image, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut image,
+        "screenWindowWidth",
+        "float",
+        &1_f32.to_le_bytes(),
+    );
+    image.push(0);
+
+    // each uncompressed block is one scanline: its y, its size, then each channel's values for the whole row
+    let row_size = width as usize * 3 * pixel_type.size();
+    let block_size = 8 + row_size;
+    let table_end = image.len() + height as usize * 8;
+    for y in 0..height as usize {
+        image.extend_from_slice(&((table_end + y * block_size) as u64).to_le_bytes());
+    }
+
+    for (y, row) in colors.chunks(width as usize).enumerate() {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        image.extend_from_slice(&(y as i32).to_le_bytes());
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        image.extend_from_slice(&(row_size as i32).to_le_bytes());
+
+        let linear: Vec<Vec3> = row
+            .iter()
+            .map(|c| color::average(c, samples_per_pixel))
+            .collect();
+        for channel in &[Vec3::b, Vec3::g, Vec3::r] {
+            for c in &linear {
+                #[allow(clippy::cast_possible_truncation)]
+                let value = channel(c) as f32;
+                match pixel_type {
+                    PixelType::Half => image.extend_from_slice(&to_half(value).to_le_bytes()),
+                    PixelType::Float => image.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        }
+    }
+
+    image
+}
+
+// Name, type name, size, then the value
+fn write_attribute(image: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    image.extend_from_slice(name.as_bytes());
+    image.push(0);
+    image.extend_from_slice(kind.as_bytes());
+    image.push(0);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    image.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    image.extend_from_slice(value);
+}
+
+// IEEE 754 half precision bits of value, rounded to the nearest even
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap
+)]
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        // infinity stays infinity, NaN stays NaN
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x0200 };
+    }
+
+    let round = |shifted: u32, dropped: u32, halfway: u32| {
+        if dropped > halfway || (dropped == halfway && shifted & 1 == 1) {
+            shifted + 1
+        } else {
+            shifted
+        }
+    };
+
+    // rebias from float's 127 to half's 15
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            // too small even for a subnormal half
+            return sign;
+        }
+        // subnormal, shift the mantissa with its implicit leading 1 down to 2^-24 units
+        let full = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let halfway = 1 << (shift - 1);
+        // rounding up into the exponent bits gives the smallest normal half, which is what it should be
+        return sign | round(full >> shift, full & ((1 << shift) - 1), halfway) as u16;
+    }
+
+    // rounding can carry into the exponent, all the way to infinity
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    sign | round(half, mantissa & 0x1fff, 0x1000) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::{image, to_half, PixelType, Vec3};
+
+    #[test]
+    fn half() {
+        assert_eq!(to_half(0.0), 0);
+        assert_eq!(to_half(-0.0), 0x8000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(0.5), 0x3800);
+        assert_eq!(to_half(-2.0), 0xc000);
+        assert_eq!(to_half(0.1), 0x2e66);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(65520.0), 0x7c00);
+        assert_eq!(to_half(f32::INFINITY), 0x7c00);
+        assert_eq!(to_half(f32::NAN) & 0x7e00, 0x7e00);
+        // subnormals
+        assert_eq!(to_half(1e-7), 0x0002);
+        assert_eq!(to_half(6.1e-5), 0x03ff);
+        assert_eq!(to_half(1e-9), 0);
+    }
+
+    #[test]
+    fn exr() {
+        let colors = vec![
+            Vec3::from_xyz(1.0, 2.0, 300.0),
+            Vec3::from_xyz(0.0, 0.5, 0.0),
+        ];
+        let exr = image(1, 2, &colors, 1, PixelType::Half);
+
+        assert_eq!(&exr[..8], [0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+        assert_eq!(&exr[8..28], b"channels\0chlist\0\x37\0\0\0");
+        assert_eq!(&exr[28..46], b"B\0\x01\0\0\0\0\0\0\0\x01\0\0\0\x01\0\0\0");
+
+        // the offset table follows the header's terminating 0 and points at each scanline block in order
+        let table = exr.len() - 2 * (8 + 6) - 16;
+        assert_eq!(exr[table - 1], 0);
+        #[allow(clippy::cast_possible_truncation)]
+        let offset = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&exr[table + i * 8..table + i * 8 + 8]);
+            u64::from_le_bytes(bytes) as usize
+        };
+        assert_eq!(offset(0), table + 16);
+        assert_eq!(offset(1), table + 16 + 14);
+
+        // y, size, then B G R
+        assert_eq!(
+            &exr[offset(0)..offset(1)],
+            [0, 0, 0, 0, 6, 0, 0, 0, 0xb0, 0x5c, 0x00, 0x40, 0x00, 0x3c]
+        );
+        assert_eq!(
+            &exr[offset(1)..],
+            [1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0x00, 0x38, 0, 0]
+        );
+
+        let exr = image(1, 2, &colors, 2, PixelType::Float);
+        assert_eq!(
+            &exr[exr.len() - 12..],
+            &[0, 0, 0, 0, 0, 0, 0x80, 0x3e, 0, 0, 0, 0]
+        );
+    }
+}
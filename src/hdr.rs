@@ -0,0 +1,333 @@
+use super::color;
+use super::image::{self, Error, Image};
+use super::vec3::Vec3;
+
+// Radiance RGBE (.hdr) images, a shared 8-bit exponent for each pixel keeps the full range of the render. Read
+// back for environment maps, in the usual top to bottom orientation.
+
+// Runs shorter than this are cheaper to write as literal bytes
+const MIN_RUN: usize = 4;
+const MAX_RUN: usize = 127;
+const MAX_LITERAL: usize = 128;
+
+pub fn image(width: u32, height: u32, colors: &[Vec3], samples_per_pixel: u32) -> Vec<u8> {
+    assert_eq!(
+        colors.len(),
+        width as usize * height as usize,
+        "{}x{} image needs a color for every pixel",
+        width,
+        height
+    );
+
+    let mut image = Vec::from(
+        format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            height, width
+        )
+        .as_bytes(),
+    );
+
+    for row in colors.chunks(width as usize) {
+        let pixels: Vec<[u8; 4]> = row
+            .iter()
+            .map(|c| rgbe(&color::average(c, samples_per_pixel)))
+            .collect();
+
+        // run length encoding only has room for widths from 8 to 32767, anything else is written flat
+        if width < 8 || width > 0x7fff {
+            for p in pixels {
+                image.extend_from_slice(&p);
+            }
+            continue;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        image.extend_from_slice(&[2, 2, (width >> 8) as u8, width as u8]);
+        for channel in 0..4 {
+            let bytes: Vec<u8> = pixels.iter().map(|p| p[channel]).collect();
+            encode_channel(&mut image, &bytes);
+        }
+    }
+
+    image
+}
+
+// Mantissas of each channel scaled by the brightest one's exponent, with the exponent offset by 128
+fn rgbe(c: &Vec3) -> [u8; 4] {
+    let brightest = c.r().max(c.g()).max(c.b());
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // brightest = mantissa * 2^exponent with mantissa in [0.5, 1)
+    #[allow(clippy::cast_possible_truncation)]
+    let mut exponent = brightest.log2().floor() as i32 + 1;
+    let mantissa = brightest / 2_f64.powi(exponent);
+    if mantissa >= 1.0 {
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        exponent -= 1;
+    }
+    if exponent > 127 {
+        // brighter than RGBE can hold
+        return [255, 255, 255, 255];
+    }
+
+    let scale = 256.0 / 2_f64.powi(exponent);
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap
+    )]
+    [
+        (c.r() * scale) as u8,
+        (c.g() * scale) as u8,
+        (c.b() * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Image, Error> {
+    let mut position = 0;
+    let mut line = || {
+        let start = position;
+        let end = bytes[start..].iter().position(|&b| b == b'\n')? + start;
+        position = end + 1;
+        Some(String::from_utf8_lossy(&bytes[start..end]).into_owned())
+    };
+    let cut_short = || Error::Invalid(String::from("HDR image is cut short"));
+
+    // header lines up to a blank one, then the resolution
+    loop {
+        let header = line().ok_or_else(cut_short)?;
+        if header.is_empty() {
+            break;
+        }
+        if header.starts_with("FORMAT=") && header != "FORMAT=32-bit_rle_rgbe" {
+            return Err(Error::Invalid(format!(
+                "HDR format \"{}\" isn't RGBE",
+                &header[7..]
+            )));
+        }
+    }
+    let resolution = line().ok_or_else(cut_short)?;
+    let words: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match words.as_slice() {
+        ["-Y", height, "+X", width] => match (height.parse(), width.parse()) {
+            (Ok(height), Ok(width)) => (height, width),
+            _ => return Err(Error::Invalid(format!("bad HDR size \"{}\"", resolution))),
+        },
+        _ => {
+            return Err(Error::Invalid(format!(
+                "HDR orientation \"{}\" isn't -Y +X",
+                resolution
+            )))
+        }
+    };
+    image::check_size(width, height)?;
+
+    // grown a row at a time, so a size the file doesn't have the data for runs out of it instead of memory
+    let mut pixels = vec![];
+    let mut row = vec![[0; 4]; width];
+    for _ in 0..height {
+        #[allow(clippy::cast_possible_truncation)]
+        let encoded = width >= 8
+            && width <= 0x7fff
+            && bytes.get(position..position + 4) == Some(&[2, 2, (width >> 8) as u8, width as u8]);
+        if encoded {
+            position += 4;
+            for channel in 0..4 {
+                decode_channel(bytes, &mut position, &mut row, channel)?;
+            }
+        } else {
+            let flat = bytes
+                .get(position..position + width * 4)
+                .ok_or_else(cut_short)?;
+            for (pixel, p) in row.iter_mut().zip(flat.chunks(4)) {
+                pixel.copy_from_slice(p);
+            }
+            position += width * 4;
+        }
+        pixels.extend(row.iter().copied().map(from_rgbe));
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// The inverse of rgbe, exactly for the values it writes
+fn from_rgbe(p: [u8; 4]) -> Vec3 {
+    if p[3] == 0 {
+        return Vec3::new();
+    }
+    let scale = 2_f64.powi(i32::from(p[3]) - 136);
+    Vec3::from_xyz(
+        f64::from(p[0]) * scale,
+        f64::from(p[1]) * scale,
+        f64::from(p[2]) * scale,
+    )
+}
+
+// One channel of a run length encoded row, as written by encode_channel
+fn decode_channel(
+    bytes: &[u8],
+    position: &mut usize,
+    row: &mut [[u8; 4]],
+    channel: usize,
+) -> Result<(), Error> {
+    let invalid = || Error::Invalid(String::from("HDR run length encoding is damaged"));
+    let mut x = 0;
+    while x < row.len() {
+        let count = *bytes.get(*position).ok_or_else(invalid)? as usize;
+        *position += 1;
+        if count > 128 {
+            let value = *bytes.get(*position).ok_or_else(invalid)?;
+            *position += 1;
+            let run = row.get_mut(x..x + count - 128).ok_or_else(invalid)?;
+            for pixel in run {
+                pixel[channel] = value;
+            }
+            x += count - 128;
+        } else {
+            let literal = bytes
+                .get(*position..*position + count)
+                .filter(|_| count > 0)
+                .ok_or_else(invalid)?;
+            let run = row.get_mut(x..x + count).ok_or_else(invalid)?;
+            for (pixel, &value) in run.iter_mut().zip(literal) {
+                pixel[channel] = value;
+            }
+            *position += count;
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+// Runs are a count above 128 followed by the repeated byte, literals are a count up to 128 followed by the bytes
+fn encode_channel(image: &mut Vec<u8>, bytes: &[u8]) {
+    let run_length = |start: usize| {
+        bytes[start..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == bytes[start])
+            .count()
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = run_length(i);
+        if run >= MIN_RUN {
+            #[allow(clippy::cast_possible_truncation)]
+            image.push(128 + run as u8);
+            image.push(bytes[i]);
+            i += run;
+            continue;
+        }
+
+        // literal bytes up to the start of the next run worth encoding
+        let start = i;
+        while i < bytes.len() && i - start < MAX_LITERAL && run_length(i) < MIN_RUN {
+            i += 1;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        image.push((i - start) as u8);
+        image.extend_from_slice(&bytes[start..i]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode_channel, image, rgbe, Error, Vec3};
+
+    fn decode_channel(encoded: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut i = 0;
+        while i < encoded.len() {
+            let count = encoded[i] as usize;
+            if count > 128 {
+                bytes.extend(std::iter::repeat(encoded[i + 1]).take(count - 128));
+                i += 2;
+            } else {
+                bytes.extend_from_slice(&encoded[i + 1..=i + count]);
+                i += 1 + count;
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn rgbe_values() {
+        assert_eq!(rgbe(&Vec3::from_xyz(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(rgbe(&Vec3::from_xyz(0.0, 1000.0, 0.25)), [0, 250, 0, 138]);
+        assert_eq!(rgbe(&Vec3::new()), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn run_length() {
+        let bytes: Vec<u8> = vec![1, 2, 3, 3, 3, 3, 3, 4, 4]
+            .into_iter()
+            .chain(std::iter::repeat(9).take(300))
+            .chain(0..200)
+            .collect();
+        let mut encoded = vec![];
+        encode_channel(&mut encoded, &bytes);
+        assert_eq!(&encoded[..6], &[2, 1, 2, 128 + 5, 3, 2]);
+        assert_eq!(decode_channel(&encoded), bytes);
+    }
+
+    #[test]
+    fn hdr() {
+        let colors = vec![Vec3::from_xyz(2.0, 2.0, 2.0); 10];
+        let hdr = image(10, 1, &colors, 2);
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 10\n";
+        assert_eq!(&hdr[..header.len()], &header[..]);
+        assert_eq!(
+            &hdr[header.len()..],
+            [2, 2, 0, 10, 138, 128, 138, 128, 138, 128, 138, 129]
+        );
+
+        // too narrow to run length encode
+        let hdr = image(2, 1, &colors[..2], 1);
+        assert_eq!(
+            &hdr[hdr.len() - 8..],
+            [128, 128, 128, 130, 128, 128, 128, 130]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let colors: Vec<Vec3> = (0..20)
+            .map(|i| {
+                // channels that share an exponent exactly, over a wide range of exponents
+                let c = f64::from(i) * 2_f64.powi(3 * (i % 4) - 4);
+                Vec3::from_xyz(c, c / 2.0, c / 4.0)
+            })
+            .collect();
+        for &(width, height) in &[(10, 2), (4, 5)] {
+            let decoded = decode(&image(width, height, &colors, 1)).unwrap();
+            assert_eq!(
+                (decoded.width, decoded.height),
+                (width as usize, height as usize)
+            );
+            assert_eq!(decoded.pixels, colors);
+        }
+
+        let hdr = image(10, 2, &colors, 1);
+        assert!(matches!(
+            decode(&hdr[..hdr.len() - 3]),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            decode(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n+X 1 -Y 1\n"),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            decode(b"#?RADIANCE\n\n+Y 1 +X 1\n"),
+            Err(Error::Invalid(_))
+        ));
+    }
+}
@@ -2,7 +2,8 @@ use super::aabb::AABB;
 use super::material::MaterialWritable;
 use super::ray::Ray;
 use super::vec3::Vec3;
-use std::rc::Rc;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug)]
@@ -13,7 +14,9 @@ pub struct Hit {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
-    pub material: Rc<dyn MaterialWritable>,
+    // Blended from the corners of a face with vertex colors
+    pub color: Option<Vec3>,
+    pub material: Arc<dyn MaterialWritable>,
 }
 
 impl PartialEq for Hit {
@@ -24,22 +27,70 @@ impl PartialEq for Hit {
             && self.u == rhs.u
             && self.v == rhs.v
             && self.front_face == rhs.front_face
+            && self.color == rhs.color
     }
 }
 
-pub trait Hittable {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit>;
+pub trait Hittable: Send + Sync {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit>;
 
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB>;
+
+    // Whether random_direction picks directions, so an emitting object is worth sampling as a light. Those that
+    // can't be sampled are only found by rays that happen to hit them.
+    fn is_sampled(&self) -> bool {
+        false
+    }
+
+    // A direction from origin towards a random point on the object, for sampling lights directly. None when it
+    // isn't sampled, or can't be from origin.
+    fn random_direction(&self, _origin: Vec3, _rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        None
+    }
+
+    // Density over solid angle of random_direction picking direction from origin, 0 if it never would
+    fn pdf_value(&self, _origin: Vec3, _direction: Vec3, _time: f64) -> f64 {
+        0.0
+    }
+}
+
+// Swaps which side of the wrapped object counts as its front face, for surfaces facing the other way
+pub struct FlipFace<T: Hittable> {
+    pub object: T,
+}
+
+impl<T: Hittable> Hittable for FlipFace<T> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        self.object.hit(r, t_min, t_max, rng).map(|mut hit| {
+            hit.front_face = !hit.front_face;
+            hit
+        })
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.object.bounding_box(t0, t1)
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.object.is_sampled()
+    }
+
+    fn random_direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        self.object.random_direction(origin, rng)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
 }
 
 impl Hittable for Vec<Box<dyn Hittable>> {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
         let mut hit = None;
         let mut closest = t_max;
 
         for obj in self {
-            if let Some(obj_hit) = obj.hit(r, t_min, closest) {
+            if let Some(obj_hit) = obj.hit(r, t_min, closest, rng) {
                 closest = obj_hit.t;
                 hit = Some(obj_hit);
             }
@@ -0,0 +1,120 @@
+use super::hdr;
+use super::png;
+use super::ppm;
+use super::tonemap::srgb_eotf;
+use super::vec3::Vec3;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Images read from disk for textures and environment maps, PPM, PNG, or Radiance HDR told apart by their first
+// bytes
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // The file isn't an image that can be read, or is damaged
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// Largest width or height accepted, so a damaged header can't ask for an enormous allocation
+const MAX_SIZE: usize = 1 << 15;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    // Row major from the top row, channels as stored in the file, from 0 to 1 for everything but HDR
+    pub pixels: Vec<Vec3>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    // Linear values for an image stored sRGB encoded, which is almost every photo or painted texture
+    pub fn decode_srgb(mut self) -> Self {
+        for p in &mut self.pixels {
+            *p = Vec3::from_xyz(srgb_eotf(p.r()), srgb_eotf(p.g()), srgb_eotf(p.b()));
+        }
+        self
+    }
+}
+
+pub fn load(path: &Path) -> Result<Image, Error> {
+    decode(&fs::read(path)?)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Image, Error> {
+    if bytes.starts_with(&png::SIGNATURE) {
+        png::decode(bytes)
+    } else if bytes.starts_with(b"P3") || bytes.starts_with(b"P6") {
+        ppm::decode(bytes)
+    } else if bytes.starts_with(b"#?") {
+        hdr::decode(bytes)
+    } else {
+        Err(Error::Invalid(String::from("not a PPM, PNG, or HDR image")))
+    }
+}
+
+// Checks the size a header gives before anything is allocated for it
+pub fn check_size(width: usize, height: usize) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        return Err(Error::Invalid(format!(
+            "{}x{} image is empty",
+            width, height
+        )));
+    }
+    if width > MAX_SIZE || height > MAX_SIZE {
+        return Err(Error::Invalid(format!(
+            "{}x{} image is larger than {} pixels across",
+            width, height, MAX_SIZE
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, load, Error, Path, Vec3};
+
+    #[test]
+    fn images() {
+        let image = decode(b"P3 1 1 255 255 0 51").unwrap();
+        assert_eq!(image.pixel(0, 0), Vec3::from_xyz(1.0, 0.0, 0.2));
+        let linear = image.decode_srgb().pixel(0, 0);
+        assert!((linear.b() - 0.033_105).abs() < 1e-6);
+
+        assert!(matches!(decode(b"GIF89a"), Err(Error::Invalid(_))));
+        assert!(matches!(decode(b"P6 0 4 255\n"), Err(Error::Invalid(_))));
+        assert!(matches!(
+            decode(b"P6 40000 4 255\n"),
+            Err(Error::Invalid(_))
+        ));
+        let huge = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 32768 +X 32768\n\x02\x02";
+        assert!(matches!(decode(huge), Err(Error::Invalid(_))));
+        assert!(matches!(
+            load(Path::new("no such image.png")),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,391 @@
+// Decompression of zlib wrapped deflate streams (RFC 1950 and 1951), enough to read PNG image data
+
+// Base lengths and extra bits for length codes 257 to 285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// Base distances and extra bits for distance codes 0 to 29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// Order the code length code lengths are given in a dynamic block's header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+const MAX_BITS: usize = 15;
+
+// The data inside a zlib stream, checked against its Adler-32
+pub fn zlib_decompress(stream: &[u8]) -> Result<Vec<u8>, String> {
+    if stream.len() < 6 {
+        return Err(String::from("zlib stream is too short"));
+    }
+    let (cmf, flags) = (stream[0], stream[1]);
+    if cmf & 0x0f != 8 || cmf >> 4 > 7 {
+        return Err(String::from("zlib stream isn't deflate compressed"));
+    }
+    if (u16::from(cmf) << 8 | u16::from(flags)) % 31 != 0 {
+        return Err(String::from("zlib header check failed"));
+    }
+    if flags & 0x20 != 0 {
+        return Err(String::from("zlib preset dictionaries aren't supported"));
+    }
+
+    let mut bits = Bits::new(&stream[2..]);
+    let data = inflate(&mut bits)?;
+
+    // blocks end on a byte boundary, or the rest of their last byte is padding
+    let end = 2 + bits.position;
+    let checksum = match stream.get(end..end + 4) {
+        Some(bytes) => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        None => return Err(String::from("zlib stream is missing its checksum")),
+    };
+    if checksum != adler32(&data) {
+        return Err(String::from("zlib checksum doesn't match the data"));
+    }
+    Ok(data)
+}
+
+// Raw deflate blocks up to and including the final one
+fn inflate(bits: &mut Bits) -> Result<Vec<u8>, String> {
+    let mut out = vec![];
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => stored(bits, &mut out)?,
+            1 => {
+                let (lengths, distances) = fixed_codes();
+                codes(bits, &mut out, &lengths, &distances)?
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(bits)?;
+                codes(bits, &mut out, &lengths, &distances)?
+            }
+            _ => return Err(String::from("invalid deflate block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+// Least significant bit first reader over a byte slice
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn read(&mut self, n: u32) -> Result<u32, String> {
+        while self.count < n {
+            let byte = match self.data.get(self.position) {
+                Some(&b) => b,
+                None => return Err(String::from("deflate stream ends early")),
+            };
+            self.position += 1;
+            self.buffer |= u32::from(byte) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    // Skips to the next byte boundary, bytes are only read when needed so what's buffered is less than one
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// Canonical Huffman code, how many codes there are of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0_u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // more codes of a length than there's room for means the lengths can't be a prefix code
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = left * 2 - i32::from(count);
+            if left < 0 {
+                return Err(String::from("invalid Huffman code lengths"));
+            }
+        }
+
+        let mut offsets = [0_u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                #[allow(clippy::cast_possible_truncation)]
+                let symbol = symbol as u16;
+                symbols[offsets[length as usize] as usize] = symbol;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    // Reads a bit at a time, codes are stored starting from their most significant bit
+    fn decode(&self, bits: &mut Bits) -> Result<u16, String> {
+        let mut code: i32 = 0;
+        // first code of the current length, and the index of its symbol
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            #[allow(clippy::cast_possible_wrap)]
+            let bit = bits.read(1)? as i32;
+            code |= bit;
+            let count = i32::from(count);
+            if code - count < first {
+                #[allow(clippy::cast_sign_loss)]
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(String::from("invalid Huffman code"))
+    }
+}
+
+fn stored(bits: &mut Bits, out: &mut Vec<u8>) -> Result<(), String> {
+    bits.align();
+    let start = bits.position;
+    let header = match bits.data.get(start..start + 4) {
+        Some(h) => h,
+        None => return Err(String::from("deflate stream ends early")),
+    };
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if len != !complement {
+        return Err(String::from("stored block length check failed"));
+    }
+    let end = start + 4 + len as usize;
+    match bits.data.get(start + 4..end) {
+        Some(block) => out.extend_from_slice(block),
+        None => return Err(String::from("deflate stream ends early")),
+    }
+    bits.position = end;
+    Ok(())
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0_u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        // 0 to 143 and 280 to 287 are 8 bits
+        *length = match symbol {
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    // the fixed lengths are always a valid code
+    (
+        Huffman::new(&lengths).unwrap(),
+        Huffman::new(&[5; 30]).unwrap(),
+    )
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(String::from("too many codes in dynamic block"));
+    }
+
+    let mut code_lengths = [0_u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        #[allow(clippy::cast_possible_truncation)]
+        let length = bits.read(3)? as u8;
+        code_lengths[symbol] = length;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    // literal/length and distance code lengths are one sequence, repeats can cross from one to the other
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(bits)?;
+        let (length, repeat) = match symbol {
+            0..=15 => {
+                #[allow(clippy::cast_possible_truncation)]
+                lengths.push(symbol as u8);
+                continue;
+            }
+            16 => match lengths.last() {
+                Some(&previous) => (previous, 3 + bits.read(2)?),
+                None => return Err(String::from("repeated code length with no previous length")),
+            },
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        if lengths.len() + repeat as usize > literal_count + distance_count {
+            return Err(String::from("code lengths run past the end of the codes"));
+        }
+        lengths.extend(std::iter::repeat(length).take(repeat as usize));
+    }
+
+    if lengths[256] == 0 {
+        return Err(String::from("dynamic block has no end of block code"));
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+// Literal bytes and length, distance pairs copying earlier output, up to the end of block code
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = lengths.decode(bits)? as usize;
+        if symbol < 256 {
+            #[allow(clippy::cast_possible_truncation)]
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+
+        let length_code = symbol - 257;
+        if length_code >= LENGTH_BASE.len() {
+            return Err(String::from("invalid length code"));
+        }
+        let length = LENGTH_BASE[length_code] as usize
+            + bits.read(u32::from(LENGTH_EXTRA[length_code]))? as usize;
+
+        let distance_code = distances.decode(bits)? as usize;
+        if distance_code >= DISTANCE_BASE.len() {
+            return Err(String::from("invalid distance code"));
+        }
+        let distance = DISTANCE_BASE[distance_code] as usize
+            + bits.read(u32::from(DISTANCE_EXTRA[distance_code]))? as usize;
+        if distance > out.len() {
+            return Err(String::from(
+                "distance reaches back before the start of the data",
+            ));
+        }
+
+        // the copy can overlap what it's writing, so go a byte at a time
+        let start = out.len() - distance;
+        for i in 0..length {
+            out.push(out[start + i]);
+        }
+    }
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    // 5552 is the most bytes that can be summed before the larger sum could overflow
+    let (a, b) = data.chunks(5552).fold((1, 0), |(mut a, mut b), chunk| {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        (a % MOD, b % MOD)
+    });
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod test {
+    use super::{adler32, zlib_decompress};
+
+    #[test]
+    fn checksum() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[0xff; 100_000]), 0x149a_302c);
+    }
+
+    #[test]
+    fn blocks() {
+        // stored
+        assert_eq!(
+            zlib_decompress(&[0x78, 0x01, 1, 3, 0, 0xfc, 0xff, 1, 2, 3, 0, 13, 0, 7]),
+            Ok(vec![1, 2, 3])
+        );
+        // fixed Huffman codes, with a length, distance pair that overlaps its own output
+        assert_eq!(
+            zlib_decompress(&FIXED),
+            Ok(b"hello hello hello hello!".to_vec())
+        );
+        // dynamic Huffman codes
+        let squares: Vec<String> = (0..60).map(|i| (i * i).to_string()).collect();
+        let expected = squares.join(" ").into_bytes();
+        assert_eq!(zlib_decompress(&DYNAMIC), Ok(expected));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(zlib_decompress(&[0x78, 0x01]).is_err());
+        // wrong header check bits
+        assert!(zlib_decompress(&[0x78, 0x02, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1]).is_err());
+        // stored length doesn't match its complement
+        assert!(zlib_decompress(&[0x78, 0x01, 1, 1, 0, 0xff, 0xff, 7, 0, 8, 0, 8]).is_err());
+        // block type 3
+        assert!(zlib_decompress(&[0x78, 0x01, 7, 0, 0, 0, 0, 0]).is_err());
+        // checksum changed
+        let mut corrupt = FIXED.to_vec();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        assert!(zlib_decompress(&corrupt).is_err());
+        // cut short
+        assert!(zlib_decompress(&DYNAMIC[..DYNAMIC.len() / 2]).is_err());
+    }
+
+    const FIXED: [u8; 17] = [
+        0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x15, 0x01, 0x70, 0xd5,
+        0x08, 0xd2,
+    ];
+    // the squares of 0 to 59, separated by spaces
+    const DYNAMIC: [u8; 136] = [
+        0x78, 0xda, 0x15, 0x8e, 0xc1, 0x01, 0xc0, 0x30, 0x08, 0x02, 0x57, 0x61, 0x04, 0x51, 0x6b,
+        0x74, 0xff, 0xc5, 0x0a, 0x2f, 0x12, 0x05, 0xbc, 0x00, 0xd1, 0x38, 0x70, 0x90, 0x1f, 0x6a,
+        0xd0, 0x87, 0x69, 0x2c, 0xc1, 0x08, 0x30, 0xa5, 0xdd, 0xda, 0xca, 0x71, 0xb2, 0xc8, 0x93,
+        0x9f, 0x74, 0x0f, 0x95, 0x2d, 0xbf, 0xd2, 0xf2, 0x75, 0x4b, 0xb7, 0xf1, 0xe5, 0xe1, 0x7b,
+        0x83, 0x91, 0x6f, 0xa4, 0x4f, 0xff, 0xa7, 0xf9, 0x6a, 0x7f, 0xf2, 0xdd, 0xb8, 0x57, 0x41,
+        0x86, 0x1a, 0x48, 0x55, 0xd1, 0x9d, 0x4c, 0x95, 0xb3, 0x7c, 0xa5, 0x7d, 0xee, 0xf3, 0xdd,
+        0x31, 0xc0, 0x98, 0xe4, 0x89, 0x88, 0xdb, 0x66, 0x10, 0x61, 0x86, 0x29, 0x68, 0xe2, 0x8c,
+        0x43, 0x56, 0x34, 0xb2, 0x83, 0x22, 0x53, 0x22, 0xc7, 0xaf, 0xe7, 0xd9, 0x7a, 0x7b, 0xf2,
+        0x95, 0x13, 0xa5, 0x7e, 0x43, 0x8b, 0xbc, 0xd4, 0x57, 0xbd, 0xfc, 0x01, 0xea, 0x2d, 0x2e,
+        0x92,
+    ];
+}
@@ -5,108 +5,232 @@
 mod aabb;
 mod bvh;
 mod camera;
+mod cli;
+mod color;
+mod constant_medium;
+mod cuboid;
+mod daylight;
+mod environment;
+mod exr;
+mod hdr;
 mod hit;
+mod image;
+mod inflate;
 mod material;
+mod matrix;
+mod mesh;
 mod moving_sphere;
+mod mtl;
+mod obj;
+mod onb;
+mod perlin;
+mod plane;
+mod ply;
+mod png;
 mod ppm;
 mod ray;
+mod rect;
+mod render;
+mod scene;
 mod sphere;
 mod texture;
+mod tonemap;
+mod transform;
+mod triangle;
 mod vec3;
+mod world;
 
+use bvh::Split;
 use camera::Camera;
+use cli::{Command, Format, Options};
+use constant_medium::ConstantMedium;
+use cuboid::Cuboid;
+use daylight::Daylight;
+use environment::{Gradient, Solid};
+use exr::PixelType;
 use hit::Hittable;
-use material::{Dielectric, Lambertian, Metal};
+use material::{Dielectric, DiffuseLight, Lambertian, MaterialWritable, Metal};
 use moving_sphere::MovingSphere;
+use plane::Plane;
+use png::BitDepth;
 use rand::prelude::*;
 use rand_pcg::Pcg64Mcg;
+use rect::{XYRect, XZRect, YZRect};
+use scene::Scene;
 use sphere::Sphere;
-use std::io::{self, Write};
-use std::rc::Rc;
-use std::thread::{self, JoinHandle};
-use std::time::{SystemTime, UNIX_EPOCH};
-use texture::{CheckerTexture, SolidColor};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use texture::{CheckerTexture, NoiseStyle, NoiseTexture, SolidColor};
 use vec3::Vec3;
+use world::World;
+
+// Builds a scene from its aspect ratio, seed, and how to split its BVH
+type SceneFn = fn(f64, u128, Split) -> Scene;
+
+// Built in scenes that can be chosen by name instead of a scene file
+const SCENES: [(&str, SceneFn); 6] = [
+    ("random_spheres", random_spheres),
+    ("two_spheres", two_spheres),
+    ("two_perlin_spheres", two_perlin_spheres),
+    ("simple_light", simple_light),
+    ("cornell_box", cornell_box),
+    ("cornell_smoke", cornell_smoke),
+];
 
 fn main() {
-    let num_threads = 8;
-
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width: u32 = 1280;
-    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-    let image_height = (f64::from(image_width) / aspect_ratio).round() as u32;
-    let samples_per_thread = 50 / num_threads;
-    let max_depth = 50;
-
-    let scene_seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-
-    let mut last_handle: Option<JoinHandle<Vec<Vec3>>> = None;
-
-    for n in 0..num_threads {
-        // render image once per thread and average out the images
-
-        // each thread adds its colors to the running vector of colors from the last thread
-        // we then divide colors by total samples during image gen to get averaged color
-        let this_last_handle = if last_handle.is_some() {
-            last_handle.take()
-        } else {
-            None
-        };
-
-        let handle = thread::spawn(move || {
-            let mut thread_colors = render_scene_slice(
-                aspect_ratio,
-                image_width,
-                image_height,
-                n,
-                samples_per_thread,
-                max_depth,
-                scene_seed,
-            );
-
-            if let Some(h) = this_last_handle {
-                let last_colors = h.join().unwrap();
-                for i in 0..thread_colors.len() {
-                    thread_colors[i] += last_colors[i]
-                }
+    let options = match cli::parse(env::args_os().skip(1)) {
+        Ok(Command::Render(o)) => o,
+        Ok(Command::Help) => {
+            let names: Vec<&str> = SCENES.iter().map(|(name, _)| *name).collect();
+            println!("{}\n\nBuilt in scenes: {}", cli::USAGE, names.join(", "));
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    let aspect_ratio = options.aspect_ratio();
+    let image_width = options.width;
+    let image_height = options.height;
+    let samples_per_pixel = options.samples_per_pixel;
+    let max_depth = options.max_depth;
+
+    let scene_seed = options.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    });
+
+    // a built in scene's name wins over a file with the same name
+    let build_start = Instant::now();
+    let split = options.split;
+    let scene = match &options.scene {
+        None => two_spheres(aspect_ratio, scene_seed, split),
+        Some(name) => match SCENES.iter().find(|(n, _)| name == n) {
+            Some((_, build)) => build(aspect_ratio, scene_seed, split),
+            None => load_scene_file(Path::new(name), aspect_ratio, scene_seed, split),
+        },
+    };
+    let scene = match options.sun {
+        Some(elevation) => Scene {
+            background: Box::new(daylight(elevation, options.turbidity)),
+            ..scene
+        },
+        None => scene,
+    };
+    eprintln!("Scene built in {:.2?}", build_start.elapsed());
+    // built once and shared read only by every thread
+    let scene = Arc::new(scene);
+
+    // open the output before rendering so a bad path doesn't throw away a finished render
+    let mut output: Box<dyn Write> = match &options.output {
+        Some(path) => match File::create(path) {
+            Ok(f) => Box::new(BufWriter::new(f)),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                process::exit(1);
             }
-            thread_colors
-        });
-        last_handle = Some(handle);
+        },
+        None => Box::new(io::stdout()),
+    };
+
+    let settings = render::Settings {
+        width: image_width,
+        height: image_height,
+        tile_size: render::TILE_SIZE,
+        samples_per_pixel,
+        max_depth,
+        seed: scene_seed,
+    };
+    let render_start = Instant::now();
+    let colors = render::render(&scene, settings, options.threads, |done, total| {
+        eprint!("\rTiles rendered: {}/{} ", done, total);
+    });
+
+    eprintln!("\nRendered in {:.2?}", render_start.elapsed());
+
+    let image = encode_image(&options, &colors);
+    if let Err(e) = output.write_all(&image).and_then(|_| output.flush()) {
+        eprintln!("\nerror writing image: {}", e);
+        process::exit(1);
     }
 
-    let colors = last_handle.unwrap().join().unwrap();
-
-    io::stdout()
-        .write_all(
-            ppm::p6_image(
-                image_width,
-                image_height,
-                &colors,
-                samples_per_thread * num_threads,
-            )
-            .as_slice(),
-        )
-        .unwrap();
-
-    eprintln!("\nDone.");
+    eprintln!("Done.");
+}
+
+// The image file for the summed samples in the chosen format
+fn encode_image(options: &Options, colors: &[Vec3]) -> Vec<u8> {
+    match options.format {
+        Format::P3 => ppm::p3_image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+            &options.tone_map,
+        ),
+        Format::P6 => ppm::p6_image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+            &options.tone_map,
+        ),
+        Format::Png => png::image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+            &options.tone_map,
+            BitDepth::Eight,
+        ),
+        Format::Png16 => png::image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+            &options.tone_map,
+            BitDepth::Sixteen,
+        ),
+        Format::Hdr => hdr::image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+        ),
+        Format::Exr => exr::image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+            PixelType::Half,
+        ),
+        Format::Exr32 => exr::image(
+            options.width,
+            options.height,
+            colors,
+            options.samples_per_pixel,
+            PixelType::Float,
+        ),
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn render_scene_slice(
-    aspect_ratio: f64,
-    image_width: u32,
-    image_height: u32,
-    slice_num: u32,
-    samples_per_pixel: u32,
-    max_depth: u16,
-    scene_seed: u128,
-) -> Vec<Vec3> {
-    // Generating the camera, scene, and its contents thread local is much easier than sharing it, even for read only
+fn load_scene_file(path: &Path, aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
+    scene::load(path, aspect_ratio, scene_seed, split).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path.display(), e);
+        process::exit(1);
+    })
+}
 
+// Camera used by the outdoor sphere scenes
+fn spheres_camera(aspect_ratio: f64) -> Camera {
     let cam_center = Vec3::from_xyz(13.0, 2.0, 3.0);
     let cam_target = Vec3::from_xyz(0.0, 0.0, 0.0);
     let cam_up = Vec3::from_xyz(0.0, 1.0, 0.0);
@@ -116,7 +240,7 @@ fn render_scene_slice(
     let cam_t1 = 0.0;
     let cam_t2 = 1.0;
 
-    let mut camera = Camera::new(
+    Camera::new(
         cam_center,
         cam_target,
         cam_up,
@@ -126,52 +250,16 @@ fn render_scene_slice(
         cam_focus_dist,
         cam_t1,
         cam_t2,
-    );
-
-    let scene = two_spheres(scene_seed);
-
-    let mut colors: Vec<Vec3> = Vec::with_capacity(image_width as usize * image_height as usize);
-
-    let mut rng = Pcg64Mcg::new(
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos()
-            + u128::from(slice_num),
-    );
-
-    for j in (0..image_height).rev() {
-        if slice_num == 0 {
-            eprint!("\rScanlines remaining: {} ", j);
-        }
-
-        for i in 0..image_width {
-            let mut pixel_color = Vec3::new();
-            for _ in 0..samples_per_pixel {
-                let u = (f64::from(i) + rng.gen_range(0.0, 1.0)) / f64::from(image_width - 1);
-                let v = (f64::from(j) + rng.gen_range(0.0, 1.0)) / f64::from(image_height - 1);
-                let r = camera.ray(&mut rng, u, v);
-                pixel_color += r.color(&scene, &mut rng, max_depth);
-            }
-            colors.push(pixel_color);
-        }
-    }
-
-    colors
+    )
 }
 
-fn random_spheres(scene_seed: u128) -> bvh::BVH {
-    let mut scene: Vec<Rc<dyn Hittable>> = vec![];
-    let ground_y = -1000.0;
-    let ground_radius = 1000.0;
-    scene.push(Rc::new(Sphere {
-        center: Vec3::from_xyz(0.0, ground_y, 0.0),
-        radius: ground_radius,
-        material: Rc::new(Lambertian::new(Box::new(CheckerTexture {
+fn random_spheres(aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
+    let mut scene: Vec<Arc<dyn Hittable>> = vec![ground(Arc::new(Lambertian::new(Box::new(
+        CheckerTexture {
             even: Box::new(SolidColor::from_rgb(0.2, 0.3, 0.1)),
             odd: Box::new(SolidColor::from_rgb(0.9, 0.9, 0.9)),
-        }))),
-    }));
+        },
+    ))))];
 
     let mut rng = Pcg64Mcg::new(scene_seed);
 
@@ -185,90 +273,314 @@ fn random_spheres(scene_seed: u128) -> bvh::BVH {
             let x = rng.gen::<f64>().mul_add(0.9, f64::from(a));
             let z = rng.gen::<f64>().mul_add(0.9, f64::from(b));
             let radius = 0.2;
-            let y = surface_y(x, z, ground_radius + radius, ground_y);
 
-            let center = Vec3::from_xyz(x, y, z);
+            let center = Vec3::from_xyz(x, radius, z);
             if (center - intersection_check).length() > 0.9 {
                 if choose_material < 0.8 {
                     let albedo =
                         Vec3::random(&mut rng, 0.0, 1.0) * Vec3::random(&mut rng, 0.0, 1.0);
                     let center1 = center + Vec3::from_xyz(0.0, rng.gen_range(0.0, 0.5), 0.0);
-                    scene.push(Rc::new(MovingSphere {
+                    scene.push(Arc::new(MovingSphere {
                         center0: center,
                         center1,
                         time0: 0.0,
                         time1: 1.0,
                         radius,
-                        material: Rc::new(Lambertian::new(Box::new(SolidColor { color: albedo }))),
+                        material: Arc::new(Lambertian::new(Box::new(SolidColor { color: albedo }))),
                     }));
                 } else if choose_material < 0.95 {
                     let albedo = Vec3::random(&mut rng, 0.5, 1.0);
                     let fuzz = rng.gen_range(0.0, 0.5);
-                    scene.push(Rc::new(Sphere {
+                    scene.push(Arc::new(Sphere {
                         center,
                         radius,
-                        material: Rc::new(Metal::new(albedo, fuzz)),
+                        material: Arc::new(Metal::new(albedo, fuzz)),
                     }));
                 } else {
-                    scene.push(Rc::new(Sphere {
+                    scene.push(Arc::new(Sphere {
                         center,
                         radius,
-                        material: Rc::new(Dielectric::new(1.5)),
+                        material: Arc::new(Dielectric::new(1.5)),
                     }));
                 }
             }
         }
     }
 
-    scene.push(Rc::new(Sphere {
+    scene.push(Arc::new(Sphere {
         center: Vec3::from_xyz(0.0, 1.0, 0.0),
         radius: 1.0,
-        material: Rc::new(Dielectric::new(1.5)),
+        material: Arc::new(Dielectric::new(1.5)),
     }));
-    scene.push(Rc::new(Sphere {
-        center: Vec3::from_xyz(
-            -4.0,
-            surface_y(-4.0, 0.0, ground_radius + 1.0, ground_y),
-            0.0,
-        ),
+    scene.push(Arc::new(Sphere {
+        center: Vec3::from_xyz(-4.0, 1.0, 0.0),
         radius: 1.0,
-        material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+        material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
             0.4, 0.2, 0.1,
         )))),
     }));
-    scene.push(Rc::new(Sphere {
-        center: Vec3::from_xyz(4.0, surface_y(4.0, 0.0, ground_radius + 1.0, ground_y), 0.0),
+    scene.push(Arc::new(Sphere {
+        center: Vec3::from_xyz(4.0, 1.0, 0.0),
         radius: 1.0,
-        material: Rc::new(Metal::new(Vec3::from_xyz(0.7, 0.6, 0.5), 0.0)),
+        material: Arc::new(Metal::new(Vec3::from_xyz(0.7, 0.6, 0.5), 0.0)),
     }));
 
-    bvh::BVH::new(&mut rng, scene, 0.0, 1.0)
+    Scene {
+        camera: spheres_camera(aspect_ratio),
+        world: World::new(&mut rng, scene, vec![], 0.0, 1.0, split),
+        background: Box::new(Gradient),
+    }
 }
 
-fn surface_y(x: f64, z: f64, combined_radius: f64, ground_y: f64) -> f64 {
-    ground_y + (x.mul_add(-x, z.mul_add(-z, combined_radius * combined_radius))).sqrt()
+// Sky with the sun elevation degrees up, off to the left of the built in scenes' cameras so shadows fall across
+// them
+fn daylight(elevation: f64, turbidity: f64) -> Daylight {
+    let (height, distance) = elevation.to_radians().sin_cos();
+    let across = Vec3::from_xyz(0.3, 0.0, 1.0).unit_vector();
+    let sun = across * distance + Vec3::from_xyz(0.0, height, 0.0);
+    Daylight::new(sun, turbidity, Vec3::from_xyz(0.3, 0.3, 0.3), 1.0).unwrap()
 }
 
-fn two_spheres(scene_seed: u128) -> bvh::BVH {
+// The y = 0 plane the outdoor scenes stand on
+fn ground(material: Arc<dyn MaterialWritable>) -> Arc<dyn Hittable> {
+    Arc::new(Plane::new(Vec3::new(), Vec3::from_xyz(0.0, 1.0, 0.0), material).unwrap())
+}
+
+fn two_spheres(aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
     let mut rng = Pcg64Mcg::new(scene_seed);
 
-    let mut objects: Vec<Rc<dyn Hittable>> = vec![];
+    let mut objects: Vec<Arc<dyn Hittable>> = vec![];
 
-    let checker = Rc::new(Lambertian::new(Box::new(CheckerTexture {
+    let checker = Arc::new(Lambertian::new(Box::new(CheckerTexture {
         even: Box::new(SolidColor::from_rgb(0.2, 0.3, 0.1)),
         odd: Box::new(SolidColor::from_rgb(0.9, 0.9, 0.9)),
     })));
 
-    objects.push(Rc::new(Sphere {
+    objects.push(Arc::new(Sphere {
         center: Vec3::from_xyz(0.0, -10.0, 0.0),
         radius: 10.0,
         material: checker.clone(),
     }));
-    objects.push(Rc::new(Sphere {
+    objects.push(Arc::new(Sphere {
         center: Vec3::from_xyz(0.0, 10.0, 0.0),
         radius: 10.0,
         material: checker,
     }));
 
-    bvh::BVH::new(&mut rng, objects, 0.0, 1.0)
+    Scene {
+        camera: spheres_camera(aspect_ratio),
+        world: World::new(&mut rng, objects, vec![], 0.0, 1.0, split),
+        background: Box::new(Gradient),
+    }
+}
+
+fn two_perlin_spheres(aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
+    let mut rng = Pcg64Mcg::new(scene_seed);
+
+    let mut objects: Vec<Arc<dyn Hittable>> = vec![];
+
+    let marble = Arc::new(Lambertian::new(Box::new(NoiseTexture::new(
+        &mut rng,
+        4.0,
+        NoiseStyle::Marble,
+    ))));
+
+    objects.push(ground(marble.clone()));
+    objects.push(Arc::new(Sphere {
+        center: Vec3::from_xyz(0.0, 2.0, 0.0),
+        radius: 2.0,
+        material: marble,
+    }));
+
+    Scene {
+        camera: spheres_camera(aspect_ratio),
+        world: World::new(&mut rng, objects, vec![], 0.0, 1.0, split),
+        background: Box::new(Gradient),
+    }
+}
+
+fn simple_light(aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
+    let mut rng = Pcg64Mcg::new(scene_seed);
+
+    let mut objects: Vec<Arc<dyn Hittable>> = vec![];
+
+    objects.push(ground(Arc::new(Lambertian::new(Box::new(
+        CheckerTexture {
+            even: Box::new(SolidColor::from_rgb(0.2, 0.3, 0.1)),
+            odd: Box::new(SolidColor::from_rgb(0.9, 0.9, 0.9)),
+        },
+    )))));
+    objects.push(Arc::new(Sphere {
+        center: Vec3::from_xyz(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            0.4, 0.2, 0.1,
+        )))),
+    }));
+    let light: Arc<dyn Hittable> = Arc::new(Sphere {
+        center: Vec3::from_xyz(-1.0, 3.0, -2.0),
+        radius: 0.75,
+        material: Arc::new(DiffuseLight::new(Box::new(SolidColor::from_rgb(
+            4.0, 4.0, 4.0,
+        )))),
+    });
+    objects.push(Arc::clone(&light));
+
+    Scene {
+        camera: spheres_camera(aspect_ratio),
+        world: World::new(&mut rng, objects, vec![light], 0.0, 1.0, split),
+        background: Box::new(Solid(Vec3::new())),
+    }
+}
+
+fn cornell_box(aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
+    let mut rng = Pcg64Mcg::new(scene_seed);
+
+    let white: Arc<dyn MaterialWritable> = Arc::new(Lambertian::new(Box::new(
+        SolidColor::from_rgb(0.73, 0.73, 0.73),
+    )));
+    let light: Arc<dyn MaterialWritable> = Arc::new(DiffuseLight::new(Box::new(
+        SolidColor::from_rgb(15.0, 15.0, 15.0),
+    )));
+
+    let mut objects = cornell_walls(&white);
+    let light: Arc<dyn Hittable> = Arc::new(XZRect {
+        x0: 213.0,
+        x1: 343.0,
+        z0: 227.0,
+        z1: 332.0,
+        k: 554.0,
+        material: light,
+    });
+    objects.push(Arc::clone(&light));
+    objects.push(Arc::new(Cuboid::new(
+        Vec3::from_xyz(130.0, 0.0, 65.0),
+        Vec3::from_xyz(295.0, 165.0, 230.0),
+        white.clone(),
+    )));
+    objects.push(Arc::new(Cuboid::new(
+        Vec3::from_xyz(265.0, 0.0, 295.0),
+        Vec3::from_xyz(430.0, 330.0, 460.0),
+        white,
+    )));
+
+    Scene {
+        camera: cornell_camera(aspect_ratio),
+        world: World::new(&mut rng, objects, vec![light], 0.0, 1.0, split),
+        background: Box::new(Solid(Vec3::new())),
+    }
+}
+
+// The Cornell box with its two blocks made of smoke, under a bigger, dimmer light
+fn cornell_smoke(aspect_ratio: f64, scene_seed: u128, split: Split) -> Scene {
+    let mut rng = Pcg64Mcg::new(scene_seed);
+
+    let white: Arc<dyn MaterialWritable> = Arc::new(Lambertian::new(Box::new(
+        SolidColor::from_rgb(0.73, 0.73, 0.73),
+    )));
+    let light: Arc<dyn MaterialWritable> = Arc::new(DiffuseLight::new(Box::new(
+        SolidColor::from_rgb(7.0, 7.0, 7.0),
+    )));
+
+    let mut objects = cornell_walls(&white);
+    let light: Arc<dyn Hittable> = Arc::new(XZRect {
+        x0: 113.0,
+        x1: 443.0,
+        z0: 127.0,
+        z1: 432.0,
+        k: 554.0,
+        material: light,
+    });
+    objects.push(Arc::clone(&light));
+    objects.push(Arc::new(ConstantMedium::isotropic(
+        Arc::new(Cuboid::new(
+            Vec3::from_xyz(130.0, 0.0, 65.0),
+            Vec3::from_xyz(295.0, 165.0, 230.0),
+            white.clone(),
+        )),
+        0.01,
+        Box::new(SolidColor::from_rgb(1.0, 1.0, 1.0)),
+    )));
+    objects.push(Arc::new(ConstantMedium::isotropic(
+        Arc::new(Cuboid::new(
+            Vec3::from_xyz(265.0, 0.0, 295.0),
+            Vec3::from_xyz(430.0, 330.0, 460.0),
+            white,
+        )),
+        0.01,
+        Box::new(SolidColor::from_rgb(0.0, 0.0, 0.0)),
+    )));
+
+    Scene {
+        camera: cornell_camera(aspect_ratio),
+        world: World::new(&mut rng, objects, vec![light], 0.0, 1.0, split),
+        background: Box::new(Solid(Vec3::new())),
+    }
+}
+
+// Green and red side walls with white floor, ceiling, and back wall, 555 units across
+fn cornell_walls(white: &Arc<dyn MaterialWritable>) -> Vec<Arc<dyn Hittable>> {
+    let red: Arc<dyn MaterialWritable> = Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+        0.65, 0.05, 0.05,
+    ))));
+    let green: Arc<dyn MaterialWritable> = Arc::new(Lambertian::new(Box::new(
+        SolidColor::from_rgb(0.12, 0.45, 0.15),
+    )));
+
+    vec![
+        Arc::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: green,
+        }),
+        Arc::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: red,
+        }),
+        Arc::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: white.clone(),
+        }),
+        Arc::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }),
+        Arc::new(XYRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }),
+    ]
+}
+
+fn cornell_camera(aspect_ratio: f64) -> Camera {
+    Camera::new(
+        Vec3::from_xyz(278.0, 278.0, -800.0),
+        Vec3::from_xyz(278.0, 278.0, 0.0),
+        Vec3::from_xyz(0.0, 1.0, 0.0),
+        40.0,
+        aspect_ratio,
+        0.0,
+        10.0,
+        0.0,
+        1.0,
+    )
 }
@@ -1,23 +1,50 @@
 use super::hit::Hit;
+use super::onb::ONB;
 use super::ray::Ray;
 use super::texture::TextureWritable;
 use super::vec3::Vec3;
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
+use std::f64::consts::PI;
 use std::fmt;
 
-pub struct Scatter {
-    pub ray: Ray,
-    pub attenuation: Vec3,
+pub enum Scatter {
+    // A direction picked at random with density pdf over solid angle, the light it brings back is weighted by
+    // the material's scattering over pdf
+    Sampled { ray: Ray, pdf: f64 },
+    // A direction (like a mirror's or through glass) that can't be evaluated for any other, carrying the
+    // attenuation of its light
+    Specular { ray: Ray, attenuation: Vec3 },
 }
 
 pub trait Material {
-    // Returns (if ray scatters) new scattered ray and attenuation of ray
+    // Returns (if ray scatters) new scattered ray and how it's weighted
     fn scatter(&self, r_in: &Ray, rng: &mut Pcg64Mcg, hit: &Hit) -> Option<Scatter>;
+
+    // Fraction of light from direction sent back along r_in per unit solid angle: the BSDF times the cosine to the
+    // normal for surfaces, or the phase function in media. Only used for materials that return Scatter::Sampled.
+    fn scattering(&self, _r_in: &Ray, _hit: &Hit, _direction: Vec3) -> Vec3 {
+        Vec3::new()
+    }
+
+    // Density over solid angle of scatter picking direction for a Scatter::Sampled
+    fn scattering_pdf(&self, _r_in: &Ray, _hit: &Hit, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    // Returns light emitted from the surface at the hit, materials that don't glow emit nothing
+    fn emitted(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+        Vec3::new()
+    }
+
+    // Whether objects made of this are added to the scene's lights, to be sampled directly
+    fn is_light(&self) -> bool {
+        false
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
-pub trait MaterialWritable: Material + fmt::Debug {}
+pub trait MaterialWritable: Material + fmt::Debug + Send + Sync {}
 
 // Lambertian
 
@@ -34,19 +61,34 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, mut rng: &mut Pcg64Mcg, hit: &Hit) -> Option<Scatter> {
-        let scatter_direction = hit.normal + Vec3::random_unit_vector(&mut rng);
-        let scattered_ray = Ray {
-            origin: hit.point,
-            direction: scatter_direction,
-            time: r_in.time,
-        };
+    // Cosine weighted about the normal, which cancels the cosine in the scattering
+    fn scatter(&self, r_in: &Ray, rng: &mut Pcg64Mcg, hit: &Hit) -> Option<Scatter> {
+        let r1: f64 = rng.gen();
+        let (sin_phi, cos_phi) = (2.0 * PI * rng.gen::<f64>()).sin_cos();
+        let z = (1.0 - r1).sqrt();
+        let direction = ONB::from_w(hit.normal).local(cos_phi * r1.sqrt(), sin_phi * r1.sqrt(), z);
 
-        Some(Scatter {
-            ray: scattered_ray,
-            attenuation: self.albedo.value(hit.u, hit.v, hit.point),
+        Some(Scatter::Sampled {
+            ray: Ray {
+                origin: hit.point,
+                direction,
+                time: r_in.time,
+            },
+            pdf: z / PI,
         })
     }
+
+    fn scattering(&self, r_in: &Ray, hit: &Hit, direction: Vec3) -> Vec3 {
+        self.albedo.value_at(hit) * self.scattering_pdf(r_in, hit, direction)
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, hit: &Hit, direction: Vec3) -> f64 {
+        let length = direction.length();
+        if length == 0.0 {
+            return 0.0;
+        }
+        (hit.normal.dot(direction) / length).max(0.0) / PI
+    }
 }
 
 impl MaterialWritable for Lambertian {}
@@ -78,7 +120,8 @@ impl Material for Metal {
                 direction: reflected + Vec3::random_in_unit_sphere(&mut rng) * self.fuzz,
                 time: r_in.time,
             };
-            return Some(Scatter {
+            // fuzz blurs the reflection without a density that can be evaluated, so it's still treated as a mirror
+            return Some(Scatter::Specular {
                 ray: scattered,
                 attenuation: self.albedo,
             });
@@ -128,7 +171,7 @@ impl Material for Dielectric {
                 direction: reflected,
                 time: r_in.time,
             };
-            return Some(Scatter {
+            return Some(Scatter::Specular {
                 ray: scattered,
                 attenuation,
             });
@@ -141,7 +184,7 @@ impl Material for Dielectric {
             time: r_in.time,
         };
 
-        Some(Scatter {
+        Some(Scatter::Specular {
             ray: scattered,
             attenuation,
         })
@@ -149,3 +192,149 @@ impl Material for Dielectric {
 }
 
 impl MaterialWritable for Dielectric {}
+
+// DiffuseLight
+
+#[derive(Debug)]
+pub struct DiffuseLight {
+    emit: Box<dyn TextureWritable>,
+}
+
+impl DiffuseLight {
+    #[allow(dead_code)]
+    pub fn new(emit: Box<dyn TextureWritable>) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rng: &mut Pcg64Mcg, _hit: &Hit) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.emit.value(u, v, p)
+    }
+
+    fn is_light(&self) -> bool {
+        true
+    }
+}
+
+impl MaterialWritable for DiffuseLight {}
+
+// Isotropic
+
+// Phase function for participating media, light scatters equally in every direction
+#[derive(Debug)]
+pub struct Isotropic {
+    albedo: Box<dyn TextureWritable>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Box<dyn TextureWritable>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r_in: &Ray, mut rng: &mut Pcg64Mcg, hit: &Hit) -> Option<Scatter> {
+        Some(Scatter::Sampled {
+            ray: Ray {
+                origin: hit.point,
+                direction: Vec3::random_unit_vector(&mut rng),
+                time: r_in.time,
+            },
+            pdf: 1.0 / (4.0 * PI),
+        })
+    }
+
+    fn scattering(&self, r_in: &Ray, hit: &Hit, direction: Vec3) -> Vec3 {
+        self.albedo.value_at(hit) * self.scattering_pdf(r_in, hit, direction)
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _hit: &Hit, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+impl MaterialWritable for Isotropic {}
+
+#[cfg(test)]
+mod test {
+    use super::{Dielectric, Hit, Isotropic, Lambertian, Material, Ray, Scatter, Vec3};
+    use crate::texture::SolidColor;
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    fn hit(normal: Vec3) -> Hit {
+        Hit {
+            point: Vec3::new(),
+            normal,
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            color: None,
+            material: Arc::new(Dielectric::new(1.5)),
+        }
+    }
+
+    // The average weight of sampled directions, which is the albedo for materials that don't lose any light
+    fn albedo<M: Material>(material: &M, hit: &Hit) -> Vec3 {
+        let mut rng = Pcg64Mcg::new(1);
+        let r_in = Ray {
+            origin: Vec3::from_xyz(0.0, 1.0, 1.0),
+            direction: Vec3::from_xyz(0.0, -1.0, -1.0),
+            time: 0.0,
+        };
+        let mut total = Vec3::new();
+        for _ in 0..10_000 {
+            match material.scatter(&r_in, &mut rng, hit) {
+                Some(Scatter::Sampled { ray, pdf }) => {
+                    // the density reported for the sampled direction is the one it was picked with
+                    let reported = material.scattering_pdf(&r_in, hit, ray.direction);
+                    assert!((reported - pdf).abs() < 1e-9);
+                    total += material.scattering(&r_in, hit, ray.direction) / pdf;
+                }
+                _ => panic!("expected a sampled scatter"),
+            }
+        }
+        total / 10_000.0
+    }
+
+    #[test]
+    fn sampled() {
+        let normal = Vec3::from_xyz(0.0, 0.6, 0.8);
+        let lambertian = Lambertian::new(Box::new(SolidColor::from_rgb(0.5, 0.25, 1.0)));
+        assert!(
+            (albedo(&lambertian, &hit(normal)) - Vec3::from_xyz(0.5, 0.25, 1.0)).length() < 1e-9
+        );
+        // nothing scatters below the surface
+        let r_in = Ray::new();
+        assert!(lambertian.scattering_pdf(&r_in, &hit(normal), -normal) < f64::EPSILON);
+        assert_eq!(
+            lambertian.scattering(&r_in, &hit(normal), -normal),
+            Vec3::new()
+        );
+
+        let isotropic = Isotropic::new(Box::new(SolidColor::from_rgb(0.5, 0.5, 0.5)));
+        assert!((albedo(&isotropic, &hit(normal)) - Vec3::from_xyz(0.5, 0.5, 0.5)).length() < 1e-9);
+    }
+
+    #[test]
+    fn specular() {
+        let glass = Dielectric::new(1.5);
+        let r_in = Ray {
+            origin: Vec3::from_xyz(0.0, 1.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let scatter = glass.scatter(
+            &r_in,
+            &mut Pcg64Mcg::new(1),
+            &hit(Vec3::from_xyz(0.0, 1.0, 0.0)),
+        );
+        assert!(matches!(scatter, Some(Scatter::Specular { .. })));
+    }
+}
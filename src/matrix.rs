@@ -0,0 +1,196 @@
+use super::vec3::Vec3;
+use std::ops;
+
+// 4x4 matrices for affine transforms, applied to column vectors so a * b transforms by b and then by a
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    // Row major
+    pub m: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub const fn identity() -> Self {
+        Self {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut t = Self::identity();
+        t.m[0][3] = offset.x;
+        t.m[1][3] = offset.y;
+        t.m[2][3] = offset.z;
+        t
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut s = Self::identity();
+        s.m[0][0] = factors.x;
+        s.m[1][1] = factors.y;
+        s.m[2][2] = factors.z;
+        s
+    }
+
+    // Counterclockwise rotation looking down the axis towards the origin (Rodrigues' formula)
+    pub fn rotation(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let mut r = Self::identity();
+        r.m[0][0] = t * a.x * a.x + cos;
+        r.m[0][1] = t * a.x * a.y - sin * a.z;
+        r.m[0][2] = t * a.x * a.z + sin * a.y;
+        r.m[1][0] = t * a.x * a.y + sin * a.z;
+        r.m[1][1] = t * a.y * a.y + cos;
+        r.m[1][2] = t * a.y * a.z - sin * a.x;
+        r.m[2][0] = t * a.x * a.z - sin * a.y;
+        r.m[2][1] = t * a.y * a.z + sin * a.x;
+        r.m[2][2] = t * a.z * a.z + cos;
+        r
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = Self::identity();
+        for (i, row) in t.m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        t
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, None for a matrix that flattens space (like a scale of 0)
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inverse = Self::identity().m;
+
+        for column in 0..4 {
+            let pivot =
+                (column..4).max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inverse[column][j] *= scale;
+            }
+            for i in (0..4).filter(|&i| i != column) {
+                let factor = a[i][column];
+                for j in 0..4 {
+                    a[i][j] -= factor * a[column][j];
+                    inverse[i][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+
+        Some(Self { m: inverse })
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        self.vector(p) + Vec3::from_xyz(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    // Directions aren't moved by the translation
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let row = |r: &[f64; 4]| r[0] * v.x + r[1] * v.y + r[2] * v.z;
+        Vec3::from_xyz(row(&self.m[0]), row(&self.m[1]), row(&self.m[2]))
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl ops::Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut product = [[0.0; 4]; 4];
+        for (i, row) in product.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Self { m: product }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Matrix4, Vec3};
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-12
+    }
+
+    #[test]
+    fn transforms() {
+        let p = Vec3::from_xyz(1.0, 2.0, 3.0);
+        assert!(close(
+            Matrix4::translation(Vec3::from_xyz(1.0, -1.0, 0.5)).point(p),
+            Vec3::from_xyz(2.0, 1.0, 3.5)
+        ));
+        assert!(close(
+            Matrix4::translation(Vec3::from_xyz(1.0, -1.0, 0.5)).vector(p),
+            p
+        ));
+        assert!(close(
+            Matrix4::scaling(Vec3::from_xyz(2.0, 1.0, -1.0)).point(p),
+            Vec3::from_xyz(2.0, 2.0, -3.0)
+        ));
+
+        // a quarter turn about y takes x to -z, and about an arbitrary axis leaves the axis alone
+        assert!(close(
+            Matrix4::rotation(Vec3::from_xyz(0.0, 1.0, 0.0), 90.0)
+                .vector(Vec3::from_xyz(1.0, 0.0, 0.0)),
+            Vec3::from_xyz(0.0, 0.0, -1.0)
+        ));
+        let axis = Vec3::from_xyz(1.0, 1.0, 1.0);
+        let turn = Matrix4::rotation(axis, 120.0);
+        assert!(close(turn.vector(axis), axis));
+        assert!(close(
+            turn.vector(Vec3::from_xyz(1.0, 0.0, 0.0)),
+            Vec3::from_xyz(0.0, 1.0, 0.0)
+        ));
+
+        // scale, then rotate, then translate
+        let m = Matrix4::translation(Vec3::from_xyz(0.0, 5.0, 0.0))
+            * Matrix4::rotation(Vec3::from_xyz(0.0, 0.0, 1.0), 90.0)
+            * Matrix4::scaling(Vec3::from_xyz(2.0, 2.0, 2.0));
+        assert!(close(
+            m.point(Vec3::from_xyz(1.0, 0.0, 0.0)),
+            Vec3::from_xyz(0.0, 7.0, 0.0)
+        ));
+        assert_eq!(m.transpose().transpose(), m);
+    }
+
+    #[test]
+    fn inverse() {
+        let m = Matrix4::translation(Vec3::from_xyz(3.0, -2.0, 1.0))
+            * Matrix4::rotation(Vec3::from_xyz(1.0, 2.0, 0.5), 33.0)
+            * Matrix4::scaling(Vec3::from_xyz(0.5, 4.0, -2.0));
+        let inverse = m.inverse().unwrap();
+        let p = Vec3::from_xyz(0.3, -7.0, 2.5);
+        assert!(close(inverse.point(m.point(p)), p));
+        assert!(close(m.point(inverse.point(p)), p));
+
+        assert_eq!(
+            Matrix4::scaling(Vec3::from_xyz(1.0, 0.0, 1.0)).inverse(),
+            None
+        );
+        assert_eq!(Matrix4::identity().inverse(), Some(Matrix4::identity()));
+    }
+}
@@ -0,0 +1,19 @@
+use super::hit::Hittable;
+use super::triangle::Triangle;
+use std::sync::Arc;
+
+// Triangles loaded from a model file
+#[derive(Debug, Default)]
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+}
+
+impl Mesh {
+    // Individual triangles, ready to be added to the rest of the scene's objects before building a BVH
+    pub fn into_hittables(self) -> Vec<Arc<dyn Hittable>> {
+        self.triangles
+            .into_iter()
+            .map(|t| Arc::new(t) as Arc<dyn Hittable>)
+            .collect()
+    }
+}
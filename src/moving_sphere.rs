@@ -5,7 +5,8 @@ use super::ray::Ray;
 use super::sphere::Sphere;
 use super::texture::SolidColor;
 use super::vec3::Vec3;
-use std::rc::Rc;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct MovingSphere {
@@ -14,7 +15,7 @@ pub struct MovingSphere {
     pub time0: f64,
     pub time1: f64,
     pub radius: f64,
-    pub material: Rc<dyn MaterialWritable>,
+    pub material: Arc<dyn MaterialWritable>,
 }
 
 impl MovingSphere {
@@ -25,7 +26,7 @@ impl MovingSphere {
             time0: 0.0,
             time1: 0.0,
             radius: 0.0,
-            material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                 0.5, 0.5, 0.5,
             )))),
         }
@@ -54,7 +55,8 @@ impl MovingSphere {
             u,
             v,
             front_face,
-            material: Rc::clone(&self.material),
+            color: None,
+            material: Arc::clone(&self.material),
         })
     }
 }
@@ -66,7 +68,7 @@ impl Default for MovingSphere {
 }
 
 impl Hittable for MovingSphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
         let oc = r.origin - self.center(r.time);
         let a = r.direction.length_squared();
         let half_b = oc.dot(r.direction);
@@ -103,8 +105,8 @@ impl Hittable for MovingSphere {
 }
 
 impl Hittable for &MovingSphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
-        (*self).hit(r, t_min, t_max)
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        (*self).hit(r, t_min, t_max, rng)
     }
 
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
@@ -115,17 +117,19 @@ impl Hittable for &MovingSphere {
 #[cfg(test)]
 #[allow(clippy::unreadable_literal)]
 mod test {
-    use super::{Hit, Hittable, Lambertian, MovingSphere, Ray, Rc, SolidColor, Vec3};
+    use super::{Arc, Hit, Hittable, Lambertian, MovingSphere, Ray, SolidColor, Vec3};
+    use rand_pcg::Pcg64Mcg;
 
     #[test]
     fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
         let s = &MovingSphere {
             center0: Vec3::from_xyz(0.0, 0.0, 1.0),
             center1: Vec3::from_xyz(0.0, 0.0, 2.0),
             time0: 0.0,
             time1: 1.0,
             radius: 0.5,
-            material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                 0.5, 0.5, 0.5,
             )))),
         };
@@ -150,7 +154,7 @@ mod test {
             time: 1.0,
         };
         assert_eq!(
-            s.hit(&hit_ray, 0.0, 2.0),
+            s.hit(&hit_ray, 0.0, 2.0, &mut rng),
             Some(Hit {
                 point: Vec3 {
                     x: 0.10787389667339245,
@@ -166,13 +170,14 @@ mod test {
                 u: 0.7133877076054168,
                 v: 0.6049005077430056,
                 front_face: true,
-                material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                color: None,
+                material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                     0.5, 0.5, 0.5
                 ))),),
             })
         );
         assert_eq!(
-            s.hit(&inside_hit_ray, 0.0, 1.0),
+            s.hit(&inside_hit_ray, 0.0, 1.0, &mut rng),
             Some(Hit {
                 point: Vec3 {
                     x: 0.0,
@@ -188,13 +193,14 @@ mod test {
                 u: 0.5,
                 v: 1.0,
                 front_face: false,
-                material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                color: None,
+                material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                     0.5, 0.5, 0.5
                 ))),),
             })
         );
-        assert_eq!(s.hit(&hit_ray, 0.0, 0.5), None);
-        assert_eq!(s.hit(&miss_ray, 0.0, 10.0), None);
-        assert_eq!(s.hit(&miss_time_ray, 0.0, 10.0), None);
+        assert_eq!(s.hit(&hit_ray, 0.0, 0.5, &mut rng), None);
+        assert_eq!(s.hit(&miss_ray, 0.0, 10.0, &mut rng), None);
+        assert_eq!(s.hit(&miss_time_ray, 0.0, 10.0, &mut rng), None);
     }
 }
@@ -0,0 +1,272 @@
+use super::image;
+use super::material::{Dielectric, Lambertian, MaterialWritable, Metal};
+use super::obj::Error;
+use super::texture::{Addressing, Filter, ImageTexture, SolidColor, TextureWritable};
+use super::vec3::Vec3;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Wavefront MTL material libraries referenced by OBJ files
+
+// The subset of an MTL material's statements that map onto this renderer's materials
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialDef {
+    pub name: String,
+    // Kd
+    pub diffuse: Vec3,
+    // Ks
+    pub specular: Vec3,
+    // Ns, phong exponent from 0 to 1000
+    pub shininess: f64,
+    // Ni
+    pub refraction_index: f64,
+    // d, or 1 - Tr
+    pub dissolve: f64,
+    pub illum: u8,
+    // map_Kd, resolved relative to the library's directory
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl MaterialDef {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            diffuse: Vec3::from_xyz(0.8, 0.8, 0.8),
+            specular: Vec3::new(),
+            shininess: 0.0,
+            refraction_index: 1.0,
+            dissolve: 1.0,
+            illum: 2,
+            diffuse_map: None,
+        }
+    }
+
+    // Transparent materials become glass, illumination models with ray traced reflection become metal,
+    // and everything else is diffuse
+    pub fn to_material(&self) -> Result<Arc<dyn MaterialWritable>, Error> {
+        match self.illum {
+            4 | 6 | 7 | 9 => return Ok(Arc::new(Dielectric::new(self.glass_index()))),
+            _ if self.dissolve < 1.0 => return Ok(Arc::new(Dielectric::new(self.glass_index()))),
+            3 | 5 | 8 => {
+                let albedo = if self.specular.length_squared() > 0.0 {
+                    self.specular
+                } else {
+                    self.diffuse
+                };
+                // rougher (lower exponent) highlights get more fuzz
+                let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+                return Ok(Arc::new(Metal::new(albedo, fuzz)));
+            }
+            _ => {}
+        }
+
+        // exporters usually leave Kd at a default grey alongside a map, so the map replaces it rather than tinting it
+        let texture: Box<dyn TextureWritable> = match &self.diffuse_map {
+            Some(path) => match image::load(path) {
+                Ok(image) => Box::new(ImageTexture {
+                    image: Arc::new(image.decode_srgb()),
+                    filter: Filter::Bilinear,
+                    addressing: Addressing::Wrap,
+                }),
+                Err(error) => {
+                    return Err(Error::Texture {
+                        path: path.clone(),
+                        error,
+                    })
+                }
+            },
+            None => Box::new(SolidColor {
+                color: self.diffuse,
+            }),
+        };
+        Ok(Arc::new(Lambertian::new(texture)))
+    }
+
+    // Exporters write Ni 1 (or leave it out) for glass that didn't set one
+    fn glass_index(&self) -> f64 {
+        if self.refraction_index > 1.0 {
+            self.refraction_index
+        } else {
+            1.5
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<HashMap<String, Arc<dyn MaterialWritable>>, Error> {
+    let defs = parse(
+        BufReader::new(File::open(path)?),
+        path.parent().unwrap_or_else(|| Path::new("")),
+    )?;
+
+    defs.iter()
+        .map(|def| Ok((def.name.clone(), def.to_material()?)))
+        .collect()
+}
+
+pub fn parse<R: BufRead>(reader: R, dir: &Path) -> Result<Vec<MaterialDef>, Error> {
+    let mut defs: Vec<MaterialDef> = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_num = i + 1;
+        let line = line?;
+        let err = |message: String| Error::Parse {
+            line: line_num,
+            message,
+        };
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) if !k.starts_with('#') => k,
+            _ => continue,
+        };
+        let args: Vec<&str> = tokens.take_while(|t| !t.starts_with('#')).collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(err(String::from("newmtl needs a name")));
+            }
+            defs.push(MaterialDef::new(args.join(" ")));
+            continue;
+        }
+
+        let def = match defs.last_mut() {
+            Some(d) => d,
+            None => return Err(err(format!("{} before any newmtl", keyword))),
+        };
+
+        match keyword {
+            "Kd" => def.diffuse = parse_color(&args).map_err(err)?,
+            "Ks" => def.specular = parse_color(&args).map_err(err)?,
+            "Ns" => def.shininess = parse_float(&args).map_err(err)?,
+            "Ni" => def.refraction_index = parse_float(&args).map_err(err)?,
+            "d" => def.dissolve = parse_float(&args).map_err(err)?,
+            "Tr" => def.dissolve = 1.0 - parse_float(&args).map_err(err)?,
+            "illum" => {
+                def.illum = match args.as_slice() {
+                    [n] => n
+                        .parse()
+                        .map_err(|_| err(format!("invalid illumination model \"{}\"", n)))?,
+                    _ => return Err(err(String::from("illum needs one model number"))),
+                }
+            }
+            "map_Kd" => {
+                // options like -s or -o come before the file name, which is always last
+                match args.last() {
+                    Some(file) => def.diffuse_map = Some(dir.join(file)),
+                    None => return Err(err(String::from("map_Kd needs a file name"))),
+                }
+            }
+            // ambient, emissive, and other maps have no equivalent here
+            _ => {}
+        }
+    }
+
+    Ok(defs)
+}
+
+fn parse_float(args: &[&str]) -> Result<f64, String> {
+    match args {
+        [n] => n.parse().map_err(|_| format!("invalid number \"{}\"", n)),
+        _ => Err(format!("expected 1 number, found {}", args.len())),
+    }
+}
+
+// A single value is used for all three channels
+fn parse_color(args: &[&str]) -> Result<Vec3, String> {
+    let values = args
+        .iter()
+        .map(|a| {
+            a.parse::<f64>()
+                .map_err(|_| format!("invalid number \"{}\"", a))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    match values.as_slice() {
+        [v] => Ok(Vec3::from_xyz(*v, *v, *v)),
+        [r, g, b] => Ok(Vec3::from_xyz(*r, *g, *b)),
+        _ => Err(format!("expected 1 or 3 numbers, found {}", values.len())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Error, Path, Vec3};
+    use std::fs;
+
+    #[test]
+    fn materials() {
+        let defs = parse(
+            "# exported
+newmtl red plastic
+Kd 0.8 0.1 0.1
+Ks 0.5
+Ns 96
+illum 2
+map_Kd -s 1 1 1 textures/red.png
+
+newmtl chrome
+Kd 0.1 0.1 0.1
+Ks 0.9 0.9 0.9
+Ns 1000
+illum 3
+
+newmtl glass
+Ni 1.45
+Tr 0.9
+"
+            .as_bytes(),
+            Path::new("models"),
+        )
+        .unwrap();
+
+        assert_eq!(defs.len(), 3);
+        assert_eq!(defs[0].name, "red plastic");
+        assert_eq!(defs[0].diffuse, Vec3::from_xyz(0.8, 0.1, 0.1));
+        assert_eq!(defs[0].specular, Vec3::from_xyz(0.5, 0.5, 0.5));
+        assert_eq!(
+            defs[0].diffuse_map,
+            Some(Path::new("models").join("textures/red.png"))
+        );
+        assert!((defs[2].dissolve - 0.1).abs() < 1e-12);
+
+        let material = |i: usize| format!("{:?}", defs[i].to_material().unwrap());
+        assert!(material(1).starts_with("Metal"));
+        assert!(material(2).starts_with("Dielectric"));
+        match defs[0].to_material() {
+            Err(Error::Texture { path, .. }) => {
+                assert_eq!(path, Path::new("models").join("textures/red.png"))
+            }
+            _ => panic!("expected texture error"),
+        }
+    }
+
+    #[test]
+    fn diffuse_map() {
+        let dir = std::env::temp_dir().join("raytracer-mtl-map");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("red.ppm"), "P3 1 1 255 255 0 0\n").unwrap();
+        let defs = parse(
+            "newmtl red\nKd 0.8 0.8 0.8\nmap_Kd red.ppm\nnewmtl plain\nKd 0.5 0.5 0.5\n".as_bytes(),
+            &dir,
+        )
+        .unwrap();
+
+        let red = format!("{:?}", defs[0].to_material().unwrap());
+        assert!(red.starts_with("Lambertian") && red.contains("ImageTexture"));
+        let plain = format!("{:?}", defs[1].to_material().unwrap());
+        assert!(plain.starts_with("Lambertian") && plain.contains("SolidColor"));
+    }
+
+    #[test]
+    fn errors() {
+        let line_of = |s: &str| match parse(s.as_bytes(), Path::new("")) {
+            Err(Error::Parse { line, .. }) => line,
+            _ => 0,
+        };
+        assert_eq!(line_of("Kd 1 1 1\n"), 1);
+        assert_eq!(line_of("newmtl a\nKd 1 1\n"), 2);
+        assert_eq!(line_of("newmtl a\n\nillum two\n"), 3);
+    }
+}
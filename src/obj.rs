@@ -0,0 +1,380 @@
+use super::image;
+use super::material::MaterialWritable;
+use super::mesh::Mesh;
+use super::mtl;
+use super::triangle::Triangle;
+use super::vec3::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Wavefront OBJ geometry: vertices, texture coordinates, normals, and polygon faces split into groups
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    // Error in a material library referenced by an OBJ
+    Library { path: PathBuf, error: Box<Error> },
+    // Image a material library uses as a texture
+    Texture { path: PathBuf, error: image::Error },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Self::Library { path, error } => write!(f, "{}: {}", path.display(), error),
+            Self::Texture { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Obj {
+    // A mesh for each g or o record that has faces, in the order they appear
+    pub groups: Vec<Mesh>,
+}
+
+impl Obj {
+    // All groups' triangles together as one mesh
+    pub fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::default();
+        for mut group in self.groups {
+            mesh.triangles.append(&mut group.triangles);
+        }
+        mesh
+    }
+}
+
+// One corner of a face, as 0 based indices into the vertex, texture coordinate, and normal lists
+#[derive(Clone, Copy)]
+struct Corner {
+    vertex: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// Faces use the given material until a usemtl names one from the file's material libraries
+pub fn load(path: &Path, material: &Arc<dyn MaterialWritable>) -> Result<Obj, Error> {
+    parse(
+        BufReader::new(File::open(path)?),
+        material,
+        path.parent().unwrap_or_else(|| Path::new("")),
+    )
+}
+
+// Material libraries are looked up relative to dir
+pub fn parse<R: BufRead>(
+    reader: R,
+    default_material: &Arc<dyn MaterialWritable>,
+    dir: &Path,
+) -> Result<Obj, Error> {
+    let mut vertices: Vec<Vec3> = vec![];
+    let mut uvs: Vec<(f64, f64)> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut materials: HashMap<String, Arc<dyn MaterialWritable>> = HashMap::new();
+    let mut material = Arc::clone(default_material);
+
+    let mut obj = Obj::default();
+    let mut group = Mesh::default();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_num = i + 1;
+        let line = line?;
+        let err = |message: String| Error::Parse {
+            line: line_num,
+            message,
+        };
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) if !k.starts_with('#') => k,
+            _ => continue,
+        };
+        let args: Vec<&str> = tokens.take_while(|t| !t.starts_with('#')).collect();
+
+        match keyword {
+            "v" => {
+                // optional w coordinate is ignored
+                let xyz = parse_floats(&args, 3, 4).map_err(err)?;
+                vertices.push(Vec3::from_xyz(xyz[0], xyz[1], xyz[2]));
+            }
+            "vt" => {
+                let uv = parse_floats(&args, 1, 3).map_err(err)?;
+                uvs.push((uv[0], uv.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let xyz = parse_floats(&args, 3, 3).map_err(err)?;
+                normals.push(Vec3::from_xyz(xyz[0], xyz[1], xyz[2]));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(err(format!(
+                        "face needs at least 3 vertices, found {}",
+                        args.len()
+                    )));
+                }
+                let corners = args
+                    .iter()
+                    .map(|a| parse_corner(a, vertices.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<Corner>, String>>()
+                    .map_err(err)?;
+
+                add_face(&mut group, &corners, (&vertices, &uvs, &normals), &material);
+            }
+            "g" | "o" => {
+                let finished = std::mem::take(&mut group);
+                if !finished.triangles.is_empty() {
+                    obj.groups.push(finished);
+                }
+            }
+            "mtllib" => {
+                for file in &args {
+                    let path = dir.join(file);
+                    match mtl::load(&path) {
+                        Ok(library) => materials.extend(library),
+                        Err(e) => {
+                            return Err(Error::Library {
+                                path,
+                                error: Box::new(e),
+                            })
+                        }
+                    }
+                }
+            }
+            "usemtl" => {
+                // materials missing from the libraries fall back to the default instead of failing the whole mesh
+                material = materials
+                    .get(&args.join(" "))
+                    .map_or_else(|| Arc::clone(default_material), Arc::clone);
+            }
+            // smoothing groups, lines, points, and anything else unsupported don't affect triangles
+            _ => {}
+        }
+    }
+
+    if !group.triangles.is_empty() {
+        obj.groups.push(group);
+    }
+
+    Ok(obj)
+}
+
+// Triangulates a polygon as a fan around its first corner
+fn add_face(
+    mesh: &mut Mesh,
+    corners: &[Corner],
+    (vertices, uvs, normals): (&[Vec3], &[(f64, f64)], &[Vec3]),
+    material: &Arc<dyn MaterialWritable>,
+) {
+    for k in 1..corners.len() - 1 {
+        let tri = [corners[0], corners[k], corners[k + 1]];
+        mesh.triangles.push(Triangle {
+            vertices: [
+                vertices[tri[0].vertex],
+                vertices[tri[1].vertex],
+                vertices[tri[2].vertex],
+            ],
+            normals: match (tri[0].normal, tri[1].normal, tri[2].normal) {
+                (Some(a), Some(b), Some(c)) => Some([normals[a], normals[b], normals[c]]),
+                _ => None,
+            },
+            uvs: match (tri[0].uv, tri[1].uv, tri[2].uv) {
+                (Some(a), Some(b), Some(c)) => Some([uvs[a], uvs[b], uvs[c]]),
+                _ => None,
+            },
+            colors: None,
+            material: Arc::clone(material),
+        });
+    }
+}
+
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min || args.len() > max {
+        return Err(format!(
+            "expected {} to {} numbers, found {}",
+            min,
+            max,
+            args.len()
+        ));
+    }
+    args.iter()
+        .map(|a| {
+            a.parse::<f64>()
+                .map_err(|_| format!("invalid number \"{}\"", a))
+        })
+        .collect()
+}
+
+// Parses "v", "v/vt", "v//vn", or "v/vt/vn"
+fn parse_corner(
+    token: &str,
+    num_vertices: usize,
+    num_uvs: usize,
+    num_normals: usize,
+) -> Result<Corner, String> {
+    let mut parts = token.split('/');
+    let vertex = resolve_index(parts.next().unwrap_or(""), num_vertices, "vertex")?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(s) => Some(resolve_index(s, num_uvs, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(s) => Some(resolve_index(s, num_normals, "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex \"{}\"", token));
+    }
+
+    Ok(Corner { vertex, uv, normal })
+}
+
+// OBJ indices start at 1, negative indices count back from the most recently defined element
+fn resolve_index(s: &str, len: usize, kind: &str) -> Result<usize, String> {
+    let index: i64 = s
+        .parse()
+        .map_err(|_| format!("invalid {} index \"{}\"", kind, s))?;
+    #[allow(clippy::cast_possible_wrap)]
+    let len = len as i64;
+    let resolved = if index > 0 { index - 1 } else { len + index };
+    if index == 0 || resolved < 0 || resolved >= len {
+        return Err(format!(
+            "{} index {} out of range, {} defined",
+            kind, index, len
+        ));
+    }
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    let resolved = resolved as usize;
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load, parse, Arc, Error, MaterialWritable, Path, Vec3};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use std::fs;
+
+    fn default_material() -> Arc<dyn MaterialWritable> {
+        Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))))
+    }
+
+    fn parse_str(s: &str) -> Result<super::Obj, Error> {
+        parse(s.as_bytes(), &default_material(), Path::new(""))
+    }
+
+    #[test]
+    fn faces() {
+        let obj = parse_str(
+            "# a quad and a triangle
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g second
+v 0 0 1
+f -5//1 -4//1 -1//1 # trailing comment
+",
+        )
+        .unwrap();
+
+        assert_eq!(obj.groups.len(), 2);
+
+        let quad = &obj.groups[0].triangles;
+        assert_eq!(quad.len(), 2);
+        assert_eq!(quad[1].vertices[0], Vec3::from_xyz(0.0, 0.0, 0.0));
+        assert_eq!(quad[1].vertices[1], Vec3::from_xyz(1.0, 1.0, 0.0));
+        assert_eq!(quad[1].vertices[2], Vec3::from_xyz(0.0, 1.0, 0.0));
+        assert_eq!(quad[1].uvs, Some([(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)]));
+        assert!(quad[1].normals.is_some());
+
+        let tri = &obj.groups[1].triangles;
+        assert_eq!(tri.len(), 1);
+        assert_eq!(tri[0].vertices[0], Vec3::from_xyz(0.0, 0.0, 0.0));
+        assert_eq!(tri[0].vertices[1], Vec3::from_xyz(1.0, 0.0, 0.0));
+        assert_eq!(tri[0].vertices[2], Vec3::from_xyz(0.0, 0.0, 1.0));
+        assert_eq!(tri[0].uvs, None);
+
+        assert_eq!(obj.into_mesh().triangles.len(), 3);
+    }
+
+    #[test]
+    fn errors() {
+        let line_of = |s: &str| match parse_str(s) {
+            Err(Error::Parse { line, .. }) => line,
+            _ => 0,
+        };
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\nf 1 2 3\n"), 3);
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2\n"), 4);
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\n\nv 0 one 0\n"), 4);
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n"), 4);
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n"), 4);
+        assert_eq!(line_of("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -4 1 2\n"), 4);
+    }
+
+    #[test]
+    fn materials() {
+        let dir = std::env::temp_dir().join("raytracer-obj-materials");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("scene.mtl"),
+            "newmtl mirror\nKs 0.9 0.9 0.9\nNs 1000\nillum 3\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("scene.obj"),
+            "mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+usemtl mirror
+f 1 2 3
+usemtl missing
+f 1 2 3
+",
+        )
+        .unwrap();
+        fs::write(dir.join("broken.obj"), "mtllib broken.mtl\n").unwrap();
+        fs::write(dir.join("broken.mtl"), "newmtl a\nNs\n").unwrap();
+
+        let triangles = load(&dir.join("scene.obj"), &default_material())
+            .unwrap()
+            .into_mesh()
+            .triangles;
+        assert!(format!("{:?}", triangles[0].material).starts_with("Lambertian"));
+        assert!(format!("{:?}", triangles[1].material).starts_with("Metal"));
+        assert!(format!("{:?}", triangles[2].material).starts_with("Lambertian"));
+
+        match load(&dir.join("broken.obj"), &default_material()) {
+            Err(Error::Library { path, error }) => {
+                assert_eq!(path, dir.join("broken.mtl"));
+                assert!(matches!(*error, Error::Parse { line: 2, .. }));
+            }
+            _ => panic!("expected library error"),
+        }
+    }
+}
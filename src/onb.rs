@@ -0,0 +1,55 @@
+use super::vec3::Vec3;
+
+// Orthonormal basis around a direction w, for turning directions picked about the z axis into ones about w
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ONB {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl ONB {
+    pub fn from_w(w: Vec3) -> Self {
+        let w = w.unit_vector();
+        // any direction that isn't parallel to w crosses with it to give one perpendicular to it
+        let other = if w.x.abs() > 0.9 {
+            Vec3::from_xyz(0.0, 1.0, 0.0)
+        } else {
+            Vec3::from_xyz(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(other).unit_vector();
+        Self {
+            u: v.cross(w),
+            v,
+            w,
+        }
+    }
+
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vec3 {
+        self.u * a + self.v * b + self.w * c
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Vec3, ONB};
+
+    #[test]
+    fn basis() {
+        for w in &[
+            Vec3::from_xyz(0.0, 0.0, 2.0),
+            Vec3::from_xyz(-1.0, 0.0, 0.0),
+            Vec3::from_xyz(0.3, -4.0, 1.2),
+        ] {
+            let basis = ONB::from_w(*w);
+            assert!((basis.w - w.unit_vector()).length() < 1e-12);
+            for (a, b) in &[(basis.u, basis.v), (basis.v, basis.w), (basis.w, basis.u)] {
+                assert!(a.dot(*b).abs() < 1e-12);
+                assert!((a.length() - 1.0).abs() < 1e-12);
+            }
+            // right handed, so u x v = w
+            assert!((basis.u.cross(basis.v) - basis.w).length() < 1e-12);
+            assert!((basis.local(0.0, 0.0, 3.0) - basis.w * 3.0).length() < 1e-12);
+        }
+    }
+}
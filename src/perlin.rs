@@ -0,0 +1,136 @@
+use super::vec3::Vec3;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+// Perlin gradient noise, the same generator always gives the same values so a seeded scene renders the same way
+
+const POINT_COUNT: usize = 256;
+
+#[derive(Debug)]
+pub struct Perlin {
+    // random unit vectors at the lattice points, dotted with the offset to p so peaks don't line up with the grid
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new<T: Rng>(rng: &mut T) -> Self {
+        let gradients = (0..POINT_COUNT)
+            .map(|_| Vec3::random(rng, -1.0, 1.0).unit_vector())
+            .collect();
+        Self {
+            gradients,
+            perm_x: permutation(rng),
+            perm_y: permutation(rng),
+            perm_z: permutation(rng),
+        }
+    }
+
+    // Smooth noise from about -1 to 1, 0 at every lattice point
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let (cell_x, fx) = split(p.x);
+        let (cell_y, fy) = split(p.y);
+        let (cell_z, fz) = split(p.z);
+
+        let mut corners = [[[Vec3::new(); 2]; 2]; 2];
+        for (dx, plane) in corners.iter_mut().enumerate() {
+            for (dy, row) in plane.iter_mut().enumerate() {
+                for (dz, corner) in row.iter_mut().enumerate() {
+                    *corner = self.gradients[self.perm_x[(cell_x + dx) % POINT_COUNT]
+                        ^ self.perm_y[(cell_y + dy) % POINT_COUNT]
+                        ^ self.perm_z[(cell_z + dz) % POINT_COUNT]];
+                }
+            }
+        }
+
+        interpolate(&corners, fx, fy, fz)
+    }
+
+    // Sum of depth octaves of noise, each at twice the frequency and half the weight of the last
+    pub fn turbulence(&self, p: Vec3, depth: u32) -> f64 {
+        let mut sum = 0.0;
+        let mut point = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            sum += weight * self.noise(point);
+            weight *= 0.5;
+            point *= 2.0;
+        }
+        sum.abs()
+    }
+}
+
+fn permutation<T: Rng>(rng: &mut T) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+    p.shuffle(rng);
+    p
+}
+
+// Lattice cell (wrapped to the permutation tables) and the fractional position within it
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap
+)]
+fn split(x: f64) -> (usize, f64) {
+    let floor = x.floor();
+    let cell = (floor as i64).rem_euclid(POINT_COUNT as i64) as usize;
+    (cell, x - floor)
+}
+
+// Trilinear blend of each corner's gradient dotted with the offset from that corner, eased with a Hermite cubic
+fn interpolate(corners: &[[[Vec3; 2]; 2]; 2], fx: f64, fy: f64, fz: f64) -> f64 {
+    let hermite = |t: f64| t * t * 2.0_f64.mul_add(-t, 3.0);
+    // how much the near (0) or far (1) corner along an axis counts
+    let weight = |corner: f64, eased: f64| corner * eased + (1.0 - corner) * (1.0 - eased);
+    let (ex, ey, ez) = (hermite(fx), hermite(fy), hermite(fz));
+
+    let mut sum = 0.0;
+    for (cx, plane) in [0.0, 1.0].iter().zip(corners) {
+        for (cy, row) in [0.0, 1.0].iter().zip(plane) {
+            for (cz, gradient) in [0.0, 1.0].iter().zip(row) {
+                let offset = Vec3::from_xyz(fx - cx, fy - cy, fz - cz);
+                sum += weight(*cx, ex) * weight(*cy, ey) * weight(*cz, ez) * gradient.dot(offset);
+            }
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Perlin, Vec3};
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn noise() {
+        let perlin = Perlin::new(&mut Pcg64Mcg::new(7));
+        let same = Perlin::new(&mut Pcg64Mcg::new(7));
+        let other = Perlin::new(&mut Pcg64Mcg::new(8));
+
+        let point = Vec3::from_xyz(1.3, -4.7, 2.2);
+        assert_eq!(perlin.noise(point).to_bits(), same.noise(point).to_bits());
+        assert!((perlin.noise(point) - other.noise(point)).abs() > 0.0);
+
+        // zero at the lattice points, and continuous between them
+        assert!(perlin.noise(Vec3::from_xyz(3.0, -2.0, 5.0)).abs() < 1e-12);
+        let near = Vec3::from_xyz(1.3, -4.7, 2.200_001);
+        assert!((perlin.noise(point) - perlin.noise(near)).abs() < 1e-4);
+
+        for i in 0..1000 {
+            let t = f64::from(i) * 0.173;
+            let n = perlin.noise(Vec3::from_xyz(t, t * 0.5, -t));
+            assert!(n.abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn turbulence() {
+        let perlin = Perlin::new(&mut Pcg64Mcg::new(7));
+        let point = Vec3::from_xyz(0.4, 0.9, -0.3);
+        assert!((perlin.turbulence(point, 1) - perlin.noise(point).abs()).abs() < 1e-12);
+        assert!(perlin.turbulence(point, 7) >= 0.0);
+    }
+}
@@ -0,0 +1,126 @@
+use super::aabb::AABB;
+use super::hit::{Hit, Hittable};
+use super::material::MaterialWritable;
+use super::onb::ONB;
+use super::ray::Ray;
+use super::vec3::Vec3;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// An infinite plane through a point, facing the way of its normal. It has no bounding box, so it's kept out of
+// the BVH by World.
+#[derive(Debug)]
+pub struct Plane {
+    point: Vec3,
+    normal: Vec3,
+    // Directions along the plane that u and v are measured in, one texture repeat per unit
+    u_axis: Vec3,
+    v_axis: Vec3,
+    pub material: Arc<dyn MaterialWritable>,
+}
+
+impl Plane {
+    // None if the normal has no length
+    pub fn new(point: Vec3, normal: Vec3, material: Arc<dyn MaterialWritable>) -> Option<Self> {
+        if normal.length_squared() == 0.0 {
+            return None;
+        }
+        let basis = ONB::from_w(normal);
+        Some(Self {
+            point,
+            normal: basis.w,
+            u_axis: basis.u,
+            v_axis: basis.v,
+            material,
+        })
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
+        let t = (self.point - r.origin).dot(self.normal) / r.direction.dot(self.normal);
+        if !(t > t_min && t < t_max) {
+            // also rejects NaN from rays along the plane
+            return None;
+        }
+
+        let point = r.at(t);
+        let front_face = r.direction.dot(self.normal) < 0.0;
+        let offset = point - self.point;
+        Some(Hit {
+            point,
+            normal: if front_face {
+                self.normal
+            } else {
+                -self.normal
+            },
+            t,
+            u: offset.dot(self.u_axis),
+            v: offset.dot(self.v_axis),
+            front_face,
+            color: None,
+            material: Arc::clone(&self.material),
+        })
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hittable, Plane, Ray, Vec3};
+    use crate::sphere::Sphere;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
+        let ground = Plane::new(
+            Vec3::from_xyz(0.0, 1.0, 0.0),
+            Vec3::from_xyz(0.0, 2.0, 0.0),
+            Sphere::default().material,
+        )
+        .unwrap();
+        assert!(ground.bounding_box(0.0, 1.0).is_none());
+
+        let down = Ray {
+            origin: Vec3::from_xyz(3.0, 5.0, -2.0),
+            direction: Vec3::from_xyz(0.0, -2.0, 0.0),
+            time: 0.0,
+        };
+        let hit = ground.hit(&down, 0.001, f64::INFINITY, &mut rng).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-12);
+        assert_eq!(hit.normal, Vec3::from_xyz(0.0, 1.0, 0.0));
+        assert!(hit.front_face);
+        // u and v are distances along the plane from its point
+        assert!((hit.u.hypot(hit.v) - 13.0_f64.sqrt()).abs() < 1e-12);
+
+        let up = Ray {
+            origin: Vec3::new(),
+            direction: Vec3::from_xyz(1.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = ground.hit(&up, 0.001, f64::INFINITY, &mut rng).unwrap();
+        assert!((hit.point - Vec3::from_xyz(1.0, 1.0, 0.0)).length() < 1e-12);
+        assert_eq!(hit.normal, Vec3::from_xyz(0.0, -1.0, 0.0));
+        assert!(!hit.front_face);
+
+        // along the plane, away from it, or past t_max
+        let along = Ray {
+            origin: Vec3::new(),
+            direction: Vec3::from_xyz(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(ground.hit(&along, 0.001, f64::INFINITY, &mut rng).is_none());
+        assert!(ground.hit(&up, 0.001, 1.0, &mut rng).is_none());
+        let away = Ray {
+            direction: Vec3::from_xyz(0.0, 1.0, 0.0),
+            ..down
+        };
+        assert!(ground.hit(&away, 0.001, f64::INFINITY, &mut rng).is_none());
+
+        assert!(Plane::new(Vec3::new(), Vec3::new(), Sphere::default().material).is_none());
+    }
+}
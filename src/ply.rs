@@ -0,0 +1,744 @@
+use super::material::MaterialWritable;
+use super::mesh::Mesh;
+use super::triangle::Triangle;
+use super::vec3::Vec3;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+// Stanford PLY polygon files, in ASCII or little or big endian binary
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // Problem in the header, or in the body of an ASCII file
+    Parse {
+        line: usize,
+        message: String,
+    },
+    // Problem in the body of a binary file, at the given item of the given element
+    Data {
+        element: String,
+        index: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Self::Data {
+                element,
+                index,
+                message,
+            } => write!(f, "{} {}: {}", element, index, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Self::I8),
+            "uchar" | "uint8" => Some(Self::U8),
+            "short" | "int16" => Some(Self::I16),
+            "ushort" | "uint16" => Some(Self::U16),
+            "int" | "int32" => Some(Self::I32),
+            "uint" | "uint32" => Some(Self::U32),
+            "float" | "float32" => Some(Self::F32),
+            "double" | "float64" => Some(Self::F64),
+            _ => None,
+        }
+    }
+
+    const fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    scalar: Scalar,
+    // Type of the item count for list properties
+    list_count: Option<Scalar>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// One property's value for an element item
+enum Value {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+// Faces use the given material. Per-vertex colors are kept on the triangles, for a vertex_color texture in
+// that material to blend across each face.
+pub fn load(path: &Path, material: &Arc<dyn MaterialWritable>) -> Result<Mesh, Error> {
+    parse(BufReader::new(File::open(path)?), material)
+}
+
+pub fn parse<R: BufRead>(
+    mut reader: R,
+    material: &Arc<dyn MaterialWritable>,
+) -> Result<Mesh, Error> {
+    let (format, elements, header_lines) = parse_header(&mut reader)?;
+
+    let mut body = vec![];
+    reader.read_to_end(&mut body)?;
+
+    let items = if format == Format::Ascii {
+        read_ascii(&body, &elements, header_lines)?
+    } else {
+        read_binary(&body, &elements, format == Format::BinaryBigEndian)?
+    };
+
+    let mut vertices: Option<(&Element, &Vec<Vec<Value>>)> = None;
+    let mut faces: Option<(&Element, &Vec<Vec<Value>>)> = None;
+    for (element, values) in elements.iter().zip(items.iter()) {
+        match element.name.as_str() {
+            "vertex" => vertices = Some((element, values)),
+            "face" => faces = Some((element, values)),
+            _ => {}
+        }
+    }
+
+    let (vertex_element, vertex_items) = match vertices {
+        Some(v) => v,
+        None => return Ok(Mesh::default()),
+    };
+    let vertex_data = VertexData::new(vertex_element, vertex_items, header_lines)?;
+
+    let (face_element, face_items) = match faces {
+        Some(f) => f,
+        None => return Ok(Mesh::default()),
+    };
+    let indices_property = face_element
+        .properties
+        .iter()
+        .position(|p| {
+            p.list_count.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index")
+        })
+        .ok_or_else(|| Error::Parse {
+            line: header_lines,
+            message: String::from("face element has no vertex_indices list"),
+        })?;
+    if let Scalar::F32 | Scalar::F64 = face_element.properties[indices_property].scalar {
+        return Err(Error::Parse {
+            line: header_lines,
+            message: String::from("vertex_indices has to be a list of integers"),
+        });
+    }
+
+    let mut triangles = vec![];
+    for (index, item) in face_items.iter().enumerate() {
+        let face_error = |message: String| Error::Data {
+            element: String::from("face"),
+            index,
+            message,
+        };
+        let corners = match &item[indices_property] {
+            Value::List(l) => l
+                .iter()
+                .map(|&i| {
+                    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+                    let vertex = i as usize;
+                    // ASCII values are read as numbers of any kind, whatever type the header gives them
+                    if !i.is_finite() || i.fract() != 0.0 {
+                        Err(face_error(format!("vertex index {} isn't an integer", i)))
+                    } else if i < 0.0 || vertex >= vertex_data.positions.len() {
+                        Err(face_error(format!("vertex index {} out of range", i)))
+                    } else {
+                        Ok(vertex)
+                    }
+                })
+                .collect::<Result<Vec<usize>, Error>>()?,
+            Value::Scalar(_) => unreachable!("vertex_indices is a list property"),
+        };
+        if corners.len() < 3 {
+            return Err(face_error(format!(
+                "face needs at least 3 vertices, found {}",
+                corners.len()
+            )));
+        }
+
+        // triangulate polygons as a fan around the first corner
+        for k in 1..corners.len() - 1 {
+            triangles.push([corners[0], corners[k], corners[k + 1]]);
+        }
+    }
+
+    Ok(vertex_data.mesh(&triangles, material))
+}
+
+// Per-vertex attributes pulled out of the vertex element's properties
+struct VertexData {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colors: Option<Vec<Vec3>>,
+}
+
+impl VertexData {
+    fn new(element: &Element, items: &[Vec<Value>], header_lines: usize) -> Result<Self, Error> {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| p.list_count.is_none() && names.contains(&p.name.as_str()))
+        };
+        let scalar = |item: &[Value], i: usize| match item[i] {
+            Value::Scalar(s) => s,
+            Value::List(_) => 0.0,
+        };
+
+        let (xi, yi, zi) = match (find(&["x"]), find(&["y"]), find(&["z"])) {
+            (Some(xi), Some(yi), Some(zi)) => (xi, yi, zi),
+            _ => {
+                return Err(Error::Parse {
+                    line: header_lines,
+                    message: String::from("vertex element needs x, y, and z properties"),
+                })
+            }
+        };
+        let positions = items
+            .iter()
+            .map(|item| Vec3::from_xyz(scalar(item, xi), scalar(item, yi), scalar(item, zi)))
+            .collect();
+
+        let normals = match (find(&["nx"]), find(&["ny"]), find(&["nz"])) {
+            (Some(nxi), Some(nyi), Some(nzi)) => Some(
+                items
+                    .iter()
+                    .map(|item| {
+                        Vec3::from_xyz(scalar(item, nxi), scalar(item, nyi), scalar(item, nzi))
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let uvs = match (
+            find(&["u", "s", "texture_u", "texture_s"]),
+            find(&["v", "t", "texture_v", "texture_t"]),
+        ) {
+            (Some(ui), Some(vi)) => Some(
+                items
+                    .iter()
+                    .map(|item| (scalar(item, ui), scalar(item, vi)))
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let colors = match (
+            find(&["red", "r", "diffuse_red"]),
+            find(&["green", "g", "diffuse_green"]),
+            find(&["blue", "b", "diffuse_blue"]),
+        ) {
+            (Some(ri), Some(gi), Some(bi)) => {
+                // integer colors are 0 to the type's max, floating point ones 0 to 1
+                let scale = match element.properties[ri].scalar {
+                    Scalar::F32 | Scalar::F64 => 1.0,
+                    Scalar::U16 | Scalar::I16 => 1.0 / 65535.0,
+                    _ => 1.0 / 255.0,
+                };
+                Some(
+                    items
+                        .iter()
+                        .map(|item| {
+                            Vec3::from_xyz(scalar(item, ri), scalar(item, gi), scalar(item, bi))
+                                * scale
+                        })
+                        .collect(),
+                )
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            positions,
+            normals,
+            uvs,
+            colors,
+        })
+    }
+
+    // Triangles between the given vertex indices
+    fn mesh(&self, triangles: &[[usize; 3]], material: &Arc<dyn MaterialWritable>) -> Mesh {
+        let triangles = triangles
+            .iter()
+            .map(|&[a, b, c]| Triangle {
+                vertices: [self.positions[a], self.positions[b], self.positions[c]],
+                normals: self.normals.as_ref().map(|n| [n[a], n[b], n[c]]),
+                uvs: self.uvs.as_ref().map(|uv| [uv[a], uv[b], uv[c]]),
+                colors: self.colors.as_ref().map(|col| [col[a], col[b], col[c]]),
+                material: Arc::clone(material),
+            })
+            .collect();
+        Mesh { triangles }
+    }
+}
+
+// Returns the body's format, its elements, and the number of header lines
+fn parse_header<R: BufRead>(reader: &mut R) -> Result<(Format, Vec<Element>, usize), Error> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut line_num = 0;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(Error::Parse {
+                line: line_num,
+                message: String::from("missing end_header"),
+            });
+        }
+        line_num += 1;
+        let err = |message: String| Error::Parse {
+            line: line_num,
+            message,
+        };
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if line_num == 1 {
+            if tokens != ["ply"] {
+                return Err(err(String::from("not a PLY file")));
+            }
+            continue;
+        }
+
+        if tokens.is_empty() || tokens[0] == "comment" || tokens[0] == "obj_info" {
+            continue;
+        }
+
+        match tokens.as_slice() {
+            ["end_header"] => break,
+            ["format", f, "1.0"] => {
+                format = Some(match *f {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(err(format!("unknown format \"{}\"", f))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: String::from(*name),
+                count: count
+                    .parse()
+                    .map_err(|_| err(format!("invalid element count \"{}\"", count)))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let property = Property {
+                    name: String::from(*name),
+                    scalar: Scalar::from_name(item)
+                        .ok_or_else(|| err(format!("unknown type \"{}\"", item)))?,
+                    list_count: Some(
+                        Scalar::from_name(count)
+                            .ok_or_else(|| err(format!("unknown type \"{}\"", count)))?,
+                    ),
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| err(String::from("property before any element")))?
+                    .properties
+                    .push(property);
+            }
+            ["property", ty, name] => {
+                let property = Property {
+                    name: String::from(*name),
+                    scalar: Scalar::from_name(ty)
+                        .ok_or_else(|| err(format!("unknown type \"{}\"", ty)))?,
+                    list_count: None,
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| err(String::from("property before any element")))?
+                    .properties
+                    .push(property);
+            }
+            _ => return Err(err(format!("invalid header line \"{}\"", line.trim()))),
+        }
+    }
+
+    // items with nothing in them take no room in a binary body, so nothing would limit how many were read
+    if let Some(element) = elements
+        .iter()
+        .find(|e| e.count > 0 && e.properties.is_empty())
+    {
+        return Err(Error::Parse {
+            line: line_num,
+            message: format!("element {} has no properties", element.name),
+        });
+    }
+
+    match format {
+        Some(f) => Ok((f, elements, line_num)),
+        None => Err(Error::Parse {
+            line: line_num,
+            message: String::from("missing format"),
+        }),
+    }
+}
+
+// ASCII bodies have one element item per line
+fn read_ascii(
+    body: &[u8],
+    elements: &[Element],
+    header_lines: usize,
+) -> Result<Vec<Vec<Vec<Value>>>, Error> {
+    let text = String::from_utf8_lossy(body);
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (header_lines + i + 1, l))
+        .filter(|(_, l)| !l.trim().is_empty());
+
+    let mut all_items = vec![];
+    for element in elements {
+        // the count comes from the header, so don't trust it further than the lines there are room for
+        let mut items = Vec::with_capacity(element.count.min(body.len()));
+        for _ in 0..element.count {
+            let (line_num, line) = lines.next().ok_or_else(|| Error::Parse {
+                line: header_lines + text.lines().count(),
+                message: format!("missing {} data", element.name),
+            })?;
+            let err = |message: String| Error::Parse {
+                line: line_num,
+                message,
+            };
+
+            let mut tokens = line.split_whitespace();
+            let mut next = || -> Result<f64, Error> {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| err(String::from("too few values")))?;
+                token
+                    .parse()
+                    .map_err(|_| err(format!("invalid number \"{}\"", token)))
+            };
+
+            let mut values = Vec::with_capacity(element.properties.len());
+            for property in &element.properties {
+                if property.list_count.is_some() {
+                    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+                    let count = next()? as usize;
+                    let list = (0..count).map(|_| next()).collect::<Result<_, _>>()?;
+                    values.push(Value::List(list));
+                } else {
+                    values.push(Value::Scalar(next()?));
+                }
+            }
+            if tokens.next().is_some() {
+                return Err(err(String::from("too many values")));
+            }
+            items.push(values);
+        }
+        all_items.push(items);
+    }
+
+    Ok(all_items)
+}
+
+fn read_binary(
+    body: &[u8],
+    elements: &[Element],
+    big_endian: bool,
+) -> Result<Vec<Vec<Vec<Value>>>, Error> {
+    let mut pos = 0;
+    let mut all_items = vec![];
+    for element in elements {
+        // every item has a property and so takes at least a byte, and the rest of the body bounds how many there can be
+        let mut items = Vec::with_capacity(element.count.min(body.len() - pos));
+        for index in 0..element.count {
+            let err = |message: String| Error::Data {
+                element: element.name.clone(),
+                index,
+                message,
+            };
+
+            let mut values = Vec::with_capacity(element.properties.len());
+            for property in &element.properties {
+                if let Some(count_type) = property.list_count {
+                    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+                    let count = read_binary_scalar(body, &mut pos, count_type, big_endian)
+                        .map_err(err)? as usize;
+                    let list = (0..count)
+                        .map(|_| read_binary_scalar(body, &mut pos, property.scalar, big_endian))
+                        .collect::<Result<_, _>>()
+                        .map_err(err)?;
+                    values.push(Value::List(list));
+                } else {
+                    values.push(Value::Scalar(
+                        read_binary_scalar(body, &mut pos, property.scalar, big_endian)
+                            .map_err(err)?,
+                    ));
+                }
+            }
+            items.push(values);
+        }
+        all_items.push(items);
+    }
+
+    Ok(all_items)
+}
+
+fn read_binary_scalar(
+    body: &[u8],
+    pos: &mut usize,
+    scalar: Scalar,
+    big_endian: bool,
+) -> Result<f64, String> {
+    let size = scalar.size();
+    let bytes = body
+        .get(*pos..*pos + size)
+        .ok_or_else(|| String::from("unexpected end of file"))?;
+    *pos += size;
+
+    // slice lengths always match the scalar's size, so the conversions can't fail
+    macro_rules! read {
+        ($t:ty) => {{
+            let b = bytes.try_into().unwrap();
+            if big_endian {
+                <$t>::from_be_bytes(b)
+            } else {
+                <$t>::from_le_bytes(b)
+            }
+        }};
+    }
+
+    Ok(match scalar {
+        Scalar::I8 => f64::from(read!(i8)),
+        Scalar::U8 => f64::from(read!(u8)),
+        Scalar::I16 => f64::from(read!(i16)),
+        Scalar::U16 => f64::from(read!(u16)),
+        Scalar::I32 => f64::from(read!(i32)),
+        Scalar::U32 => f64::from(read!(u32)),
+        Scalar::F32 => f64::from(read!(f32)),
+        Scalar::F64 => read!(f64),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Arc, Error, MaterialWritable, Vec3};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+
+    fn default_material() -> Arc<dyn MaterialWritable> {
+        Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            0.5, 0.5, 0.5,
+        ))))
+    }
+
+    #[test]
+    fn ascii() {
+        let quad = |colors: bool| {
+            let (property, values) = if colors {
+                (
+                    "property uchar red\nproperty uchar green\nproperty uchar blue\n",
+                    ["255 0 0", "0 255 0", "0 0 255", "255 255 255"],
+                )
+            } else {
+                ("", [""; 4])
+            };
+            format!(
+                "ply
+format ascii 1.0
+comment a quad
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+{}element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 0 0 {}
+1 0 0 0 0 1 1 0 {}
+1 1 0 0 0 1 1 1 {}
+0 1 0 0 0 1 0 1 {}
+4 0 1 2 3
+",
+                property, values[0], values[1], values[2], values[3]
+            )
+        };
+        let material = default_material();
+        // compares what the pointers point at, not their vtables
+        let same = |a: &Arc<dyn MaterialWritable>, b: &Arc<dyn MaterialWritable>| {
+            std::ptr::eq(Arc::as_ptr(a).cast::<u8>(), Arc::as_ptr(b).cast::<u8>())
+        };
+
+        let mesh = parse(quad(false).as_bytes(), &material).unwrap();
+        assert_eq!(mesh.triangles.len(), 2);
+        let tri = &mesh.triangles[1];
+        assert_eq!(tri.vertices[1], Vec3::from_xyz(1.0, 1.0, 0.0));
+        assert_eq!(tri.uvs, Some([(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)]));
+        assert!(tri.normals.is_some());
+        assert!(same(&tri.material, &material));
+        assert_eq!(tri.colors, None);
+
+        // vertex colors are carried alongside the file's texture coordinates and the given material
+        let colored = parse(quad(true).as_bytes(), &material).unwrap();
+        let colored_tri = &colored.triangles[1];
+        assert!(same(&colored_tri.material, &material));
+        assert_eq!(colored_tri.uvs, tri.uvs);
+        assert_eq!(
+            colored_tri.colors,
+            Some([
+                Vec3::from_xyz(1.0, 0.0, 0.0),
+                Vec3::from_xyz(0.0, 0.0, 1.0),
+                Vec3::from_xyz(1.0, 1.0, 1.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn binary() {
+        let header = |format: &str| {
+            format!(
+                "ply\nformat {} 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty double z\nelement face 1\nproperty list uchar uint vertex_index\nend_header\n",
+                format
+            )
+            .into_bytes()
+        };
+        let vertices = [[0.0_f32, 0.0], [2.0, 0.0], [0.0, 2.0]];
+
+        let mut little = header("binary_little_endian");
+        let mut big = header("binary_big_endian");
+        for v in &vertices {
+            little.extend_from_slice(&v[0].to_le_bytes());
+            little.extend_from_slice(&v[1].to_le_bytes());
+            little.extend_from_slice(&1.5_f64.to_le_bytes());
+            big.extend_from_slice(&v[0].to_be_bytes());
+            big.extend_from_slice(&v[1].to_be_bytes());
+            big.extend_from_slice(&1.5_f64.to_be_bytes());
+        }
+        little.push(3);
+        big.push(3);
+        for i in 0..3_u32 {
+            little.extend_from_slice(&i.to_le_bytes());
+            big.extend_from_slice(&i.to_be_bytes());
+        }
+
+        for data in &[little, big] {
+            let mesh = parse(data.as_slice(), &default_material()).unwrap();
+            assert_eq!(mesh.triangles.len(), 1);
+            assert_eq!(
+                mesh.triangles[0].vertices,
+                [
+                    Vec3::from_xyz(0.0, 0.0, 1.5),
+                    Vec3::from_xyz(2.0, 0.0, 1.5),
+                    Vec3::from_xyz(0.0, 2.0, 1.5)
+                ]
+            );
+            assert!(format!("{:?}", mesh.triangles[0].material).contains("SolidColor"));
+
+            let truncated = &data[..data.len() - 2];
+            assert!(matches!(
+                parse(truncated, &default_material()),
+                Err(Error::Data { index: 0, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let line_of = |s: &str| match parse(s.as_bytes(), &default_material()) {
+            Err(Error::Parse { line, .. }) => line,
+            _ => 0,
+        };
+        assert_eq!(line_of("obj\n"), 1);
+        assert_eq!(line_of("ply\nformat ascii 1.0\nproperty float x\n"), 3);
+        assert_eq!(
+            line_of("ply\nformat ascii 1.0\nelement vertex 1\nproperty flt x\n"),
+            4
+        );
+        assert_eq!(
+            line_of("ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n1 x 0\n"),
+            9
+        );
+        assert_eq!(
+            line_of(
+                "ply\nformat ascii 1.0\nelement vertex 4000000000\nelement face 1\nend_header\n"
+            ),
+            5
+        );
+        // a count far beyond what the body holds runs out of data instead of memory
+        let huge = b"ply\nformat binary_little_endian 1.0\nelement vertex 4000000000\nproperty float x\nend_header\n\0\0\0\0";
+        assert!(matches!(
+            parse(&huge[..], &default_material()),
+            Err(Error::Data { index: 1, .. })
+        ));
+        let triangle = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar {} vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n{}\n";
+        assert_eq!(
+            line_of(
+                &triangle
+                    .replacen("{}", "float", 1)
+                    .replacen("{}", "3 0 1 2", 1)
+            ),
+            9
+        );
+        for indices in &["3 0 1.7 2", "3 0 1 NaN", "3 0 inf 1"] {
+            let source = triangle.replacen("{}", "int", 1).replacen("{}", indices, 1);
+            assert!(matches!(
+                parse(source.as_bytes(), &default_material()),
+                Err(Error::Data { index: 0, .. })
+            ));
+        }
+        assert!(matches!(
+            parse(
+                "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n".as_bytes(),
+                &default_material()
+            ),
+            Err(Error::Data { index: 0, .. })
+        ));
+    }
+}
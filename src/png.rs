@@ -0,0 +1,502 @@
+use super::color;
+use super::image::{self, Error, Image};
+use super::inflate::{self, adler32};
+use super::tonemap::ToneMap;
+use super::vec3::Vec3;
+
+// PNG images written with uncompressed (stored) deflate blocks, so there's no compressor to get wrong,
+// and read back in any of the standard formats for textures
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const COLOR_TYPE_GRAY: u8 = 0;
+// RGB without alpha
+const COLOR_TYPE_RGB: u8 = 2;
+const COLOR_TYPE_PALETTE: u8 = 3;
+const COLOR_TYPE_GRAY_ALPHA: u8 = 4;
+const COLOR_TYPE_RGBA: u8 = 6;
+// Largest length a stored deflate block can hold
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+pub fn image(
+    width: u32,
+    height: u32,
+    colors: &[Vec3],
+    samples_per_pixel: u32,
+    tone_map: &ToneMap,
+    depth: BitDepth,
+) -> Vec<u8> {
+    assert_eq!(
+        colors.len(),
+        width as usize * height as usize,
+        "{}x{} image needs a color for every pixel",
+        width,
+        height
+    );
+
+    let bytes_per_pixel = match depth {
+        BitDepth::Eight => 3,
+        BitDepth::Sixteen => 6,
+    };
+
+    // every scanline starts with its filter type, always 0 for none
+    let mut scanlines =
+        Vec::with_capacity(height as usize * (1 + width as usize * bytes_per_pixel));
+    for row in colors.chunks(width as usize) {
+        scanlines.push(0);
+        for c in row {
+            let c = color::display(c, samples_per_pixel, tone_map);
+            for &channel in &[c.r(), c.g(), c.b()] {
+                match depth {
+                    BitDepth::Eight => scanlines.push(color::to_u8(channel)),
+                    BitDepth::Sixteen => {
+                        scanlines.extend_from_slice(&color::to_u16(channel).to_be_bytes())
+                    }
+                }
+            }
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.push(match depth {
+        BitDepth::Eight => 8,
+        BitDepth::Sixteen => 16,
+    });
+    // color type, then deflate compression, adaptive filtering, and no interlacing
+    header.extend_from_slice(&[COLOR_TYPE_RGB, 0, 0, 0]);
+
+    let mut png = Vec::from(&SIGNATURE[..]);
+    write_chunk(&mut png, *b"IHDR", &header);
+    write_chunk(&mut png, *b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, *b"IEND", &[]);
+    png
+}
+
+// Length, type, data, then a CRC of the type and data
+fn write_chunk(png: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    #[allow(clippy::cast_possible_truncation)]
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(&kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// A zlib stream of deflate blocks that store the data as is
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = (data.len() + MAX_STORED_BLOCK - 1) / MAX_STORED_BLOCK;
+    let mut stream = Vec::with_capacity(data.len() + blocks.max(1) * 5 + 6);
+    // deflate with a 32K window, and check bits that make the header a multiple of 31
+    stream.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        // an empty stream still needs one final block
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        // BFINAL on the last block, BTYPE 00 for stored
+        stream.push(if last { 1 } else { 0 });
+        #[allow(clippy::cast_possible_truncation)]
+        let len = chunk.len() as u16;
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(chunk);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0_u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+
+    !data.iter().fold(0xffff_ffff, |crc, &b| {
+        table[((crc ^ u32::from(b)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+// The color of every pixel, alpha is dropped
+pub fn decode(bytes: &[u8]) -> Result<Image, Error> {
+    let invalid = |message: &str| Error::Invalid(format!("PNG {}", message));
+
+    let mut header = None;
+    let mut palette: Vec<Vec3> = vec![];
+    let mut compressed = vec![];
+    let mut position = SIGNATURE.len();
+    loop {
+        let (kind, data) = match read_chunk(bytes, &mut position) {
+            Some(chunk) => chunk,
+            None => return Err(invalid("file is cut short or damaged")),
+        };
+        match &kind {
+            b"IHDR" => header = Some(Header::new(data)?),
+            b"PLTE" => {
+                palette = data
+                    .chunks_exact(3)
+                    .map(|c| {
+                        let channel = |b: u8| f64::from(b) / 255.0;
+                        Vec3::from_xyz(channel(c[0]), channel(c[1]), channel(c[2]))
+                    })
+                    .collect()
+            }
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            // an uppercase first letter marks a chunk needed to show the image correctly
+            _ if kind[0].is_ascii_uppercase() => {
+                return Err(invalid(&format!(
+                    "chunk {} isn't supported",
+                    String::from_utf8_lossy(&kind)
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    let header = match header {
+        Some(h) => h,
+        None => return Err(invalid("file has no IHDR chunk")),
+    };
+    let data = inflate::zlib_decompress(&compressed).map_err(|e| invalid(&e))?;
+    let scanlines = header.unfilter(&data)?;
+
+    let max = f64::from((1_u32 << header.depth) - 1);
+    let mut pixels = Vec::with_capacity(header.width * header.height);
+    for row in scanlines.chunks(header.stride()) {
+        let sample = |i: usize| sample(row, i, header.depth);
+        for x in 0..header.width {
+            let first = x * header.channels();
+            let channel = |c: usize| f64::from(sample(first + c)) / max;
+            pixels.push(match header.color_type {
+                COLOR_TYPE_PALETTE => match palette.get(sample(first) as usize) {
+                    Some(&color) => color,
+                    None => return Err(invalid("palette index is past the end of the palette")),
+                },
+                COLOR_TYPE_RGB | COLOR_TYPE_RGBA => {
+                    Vec3::from_xyz(channel(0), channel(1), channel(2))
+                }
+                _ => Vec3::from_xyz(channel(0), channel(0), channel(0)),
+            });
+        }
+    }
+
+    Ok(Image {
+        width: header.width,
+        height: header.height,
+        pixels,
+    })
+}
+
+// A chunk's type and data if its CRC matches, moving past it
+fn read_chunk<'a>(bytes: &'a [u8], position: &mut usize) -> Option<([u8; 4], &'a [u8])> {
+    let start = *position;
+    let length = bytes.get(start..start + 4)?;
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    let end = start.checked_add(8 + length)?;
+    let checked = bytes.get(start + 4..end)?;
+    let crc = bytes.get(end..end + 4)?;
+    if crc32(checked).to_be_bytes() != crc {
+        return None;
+    }
+    *position = end + 4;
+    Some((
+        [checked[0], checked[1], checked[2], checked[3]],
+        &checked[4..],
+    ))
+}
+
+// The sample at index i of a scanline of depth bit samples, packed from the most significant bit
+fn sample(row: &[u8], i: usize, depth: u8) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+        8 => u16::from(row[i]),
+        _ => {
+            let per_byte = 8 / depth as usize;
+            #[allow(clippy::cast_possible_truncation)]
+            let shift = 8 - depth - (i % per_byte) as u8 * depth;
+            u16::from(row[i / per_byte] >> shift) & ((1 << depth) - 1)
+        }
+    }
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn new(data: &[u8]) -> Result<Self, Error> {
+        let invalid = |message: String| Error::Invalid(format!("PNG {}", message));
+        if data.len() != 13 {
+            return Err(invalid(String::from("IHDR chunk is the wrong size")));
+        }
+        let dimension = |i: usize| {
+            u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize
+        };
+        let header = Self {
+            width: dimension(0),
+            height: dimension(4),
+            depth: data[8],
+            color_type: data[9],
+        };
+        image::check_size(header.width, header.height)?;
+
+        let depths: &[u8] = match header.color_type {
+            COLOR_TYPE_GRAY => &[1, 2, 4, 8, 16],
+            COLOR_TYPE_PALETTE => &[1, 2, 4, 8],
+            COLOR_TYPE_RGB | COLOR_TYPE_GRAY_ALPHA | COLOR_TYPE_RGBA => &[8, 16],
+            other => return Err(invalid(format!("color type {} isn't valid", other))),
+        };
+        if !depths.contains(&header.depth) {
+            return Err(invalid(format!(
+                "bit depth {} isn't valid for color type {}",
+                header.depth, header.color_type
+            )));
+        }
+        // compression and filter method have only ever had one value
+        if data[10] != 0 || data[11] != 0 {
+            return Err(invalid(String::from(
+                "compression or filter method isn't 0",
+            )));
+        }
+        if data[12] != 0 {
+            return Err(invalid(String::from("interlaced images aren't supported")));
+        }
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_TYPE_RGB => 3,
+            COLOR_TYPE_GRAY_ALPHA => 2,
+            COLOR_TYPE_RGBA => 4,
+            _ => 1,
+        }
+    }
+
+    // Bytes in a scanline, not counting its filter type
+    fn stride(&self) -> usize {
+        (self.width * self.channels() * self.depth as usize + 7) / 8
+    }
+
+    // Scanlines with their filters undone and filter type bytes removed
+    fn unfilter(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let stride = self.stride();
+        if data.len() < self.height * (stride + 1) {
+            return Err(Error::Invalid(String::from("PNG image data is cut short")));
+        }
+        // filters work on corresponding bytes of the previous pixel, or the previous byte below 8 bits
+        let left = ((self.channels() * self.depth as usize) / 8).max(1);
+
+        let mut out = vec![0; self.height * stride];
+        let mut previous = vec![0; stride];
+        for (filtered, row) in data.chunks(stride + 1).zip(out.chunks_mut(stride)) {
+            let filter = filtered[0];
+            for i in 0..stride {
+                let a = if i >= left { row[i - left] } else { 0 };
+                let b = previous[i];
+                let c = if i >= left { previous[i - left] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    #[allow(clippy::cast_possible_truncation)]
+                    3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                    4 => paeth(a, b, c),
+                    other => {
+                        return Err(Error::Invalid(format!(
+                            "PNG filter type {} isn't valid",
+                            other
+                        )))
+                    }
+                };
+                row[i] = filtered[1 + i].wrapping_add(predicted);
+            }
+            previous.copy_from_slice(row);
+        }
+        Ok(out)
+    }
+}
+
+// Whichever of left, up, or up left is closest to left + up - up left
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let pa = (p - i16::from(a)).abs();
+    let pb = (p - i16::from(b)).abs();
+    let pc = (p - i16::from(c)).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        adler32, crc32, decode, image, write_chunk, zlib_stored, BitDepth, Error, ToneMap, Vec3,
+        SIGNATURE,
+    };
+
+    // PNG of the given IHDR fields and scanlines, with a palette chunk if there are any entries
+    fn encode(header: [u8; 13], palette: &[u8], scanlines: &[u8]) -> Vec<u8> {
+        let mut png = Vec::from(&SIGNATURE[..]);
+        write_chunk(&mut png, *b"IHDR", &header);
+        if !palette.is_empty() {
+            write_chunk(&mut png, *b"PLTE", palette);
+        }
+        write_chunk(&mut png, *b"tEXt", b"Comment\0ignored");
+        write_chunk(&mut png, *b"IDAT", &zlib_stored(scanlines));
+        write_chunk(&mut png, *b"IEND", &[]);
+        png
+    }
+
+    fn header(width: u8, height: u8, depth: u8, color_type: u8) -> [u8; 13] {
+        [0, 0, 0, width, 0, 0, 0, height, depth, color_type, 0, 0, 0]
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn stored_blocks() {
+        let data: Vec<u8> = (0..251).cycle().take(70_000).collect();
+        let stream = zlib_stored(&data);
+        assert_eq!(stream.len(), 2 + 5 + 65535 + 5 + (70_000 - 65535) + 4);
+        assert_eq!(&stream[..7], &[0x78, 0x01, 0, 0xff, 0xff, 0, 0]);
+        assert_eq!(&stream[65542..65547], &[1, 0x71, 0x11, 0x8e, 0xee]);
+        assert_eq!(&stream[stream.len() - 4..], &adler32(&data).to_be_bytes());
+
+        assert_eq!(
+            zlib_stored(&[]),
+            [0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1]
+        );
+    }
+
+    #[test]
+    fn png() {
+        let colors = vec![
+            Vec3::from_xyz(1.0, 0.0, 0.25),
+            Vec3::from_xyz(0.0, 4.0, 0.0),
+        ];
+
+        let png = image(2, 1, &colors, 1, &ToneMap::default(), BitDepth::Eight);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(
+            &png[8..33],
+            [
+                0, 0, 0, 13, b'I', b'H', b'D', b'R', 0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0, 0x7b,
+                0x40, 0xe8, 0xdd
+            ]
+        );
+        // IDAT: zlib header, one final stored block of the filter byte and two pixels, then the Adler-32
+        assert_eq!(&png[33..41], [0, 0, 0, 18, b'I', b'D', b'A', b'T']);
+        assert_eq!(
+            &png[41..59],
+            [0x78, 0x01, 1, 7, 0, 0xf8, 0xff, 0, 255, 0, 137, 0, 255, 0, 0x0a, 0x23, 0x02, 0x88]
+        );
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+
+        let png = image(2, 1, &colors, 1, &ToneMap::default(), BitDepth::Sixteen);
+        assert_eq!(png[24], 16);
+        assert_eq!(
+            &png[48..61],
+            [0, 255, 255, 0, 0, 0x89, 0x7f, 0, 0, 255, 255, 0, 0]
+        );
+    }
+
+    #[test]
+    fn decode_png() {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let bytes = |c: Vec3| {
+            [c.r(), c.g(), c.b()]
+                .iter()
+                .map(|v| (v * 255.0).round() as u8)
+                .collect::<Vec<u8>>()
+        };
+
+        // sub, paeth, then average filtered rows of 8-bit RGB
+        let png = encode(
+            header(3, 3, 8, 2),
+            &[],
+            &[
+                1, 10, 20, 30, 5, 5, 5, 5, 5, 5, 4, 2, 2, 2, 255, 1, 251, 235, 226, 5, 3, 194, 89,
+                240, 156, 202, 252, 185, 66, 55,
+            ],
+        );
+        let decoded = decode(&png).unwrap();
+        assert_eq!((decoded.width, decoded.height), (3, 3));
+        assert_eq!(bytes(decoded.pixel(2, 0)), [20, 30, 40]);
+        assert_eq!(bytes(decoded.pixel(1, 1)), [14, 26, 30]);
+        assert_eq!(bytes(decoded.pixel(2, 1)), [255, 0, 40]);
+        assert_eq!(bytes(decoded.pixel(2, 2)), [60, 70, 80]);
+
+        // 2-bit palette indices, 16-bit grey with alpha
+        let png = encode(
+            header(4, 1, 2, 3),
+            &[0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255],
+            &[0, 0b0001_1011],
+        );
+        let decoded = decode(&png).unwrap();
+        assert_eq!(decoded.pixels[1], Vec3::from_xyz(1.0, 0.0, 0.0));
+        assert_eq!(decoded.pixels[2], Vec3::from_xyz(0.0, 1.0, 0.0));
+        assert!(matches!(
+            decode(&encode(header(4, 1, 2, 3), &[0; 6], &[0, 0b0001_1011])),
+            Err(Error::Invalid(_))
+        ));
+
+        let decoded = decode(&encode(header(1, 1, 16, 4), &[], &[0, 0x80, 0, 0xff, 0xff])).unwrap();
+        assert!((decoded.pixels[0].b() - 32768.0 / 65535.0).abs() < 1e-12);
+
+        // what the writer wrote
+        let colors = vec![
+            Vec3::from_xyz(1.0, 0.0, 0.25),
+            Vec3::from_xyz(0.0, 4.0, 0.0),
+        ];
+        let written = image(2, 1, &colors, 1, &ToneMap::default(), BitDepth::Eight);
+        let decoded = decode(&written).unwrap();
+        assert_eq!(bytes(decoded.pixels[0]), [255, 0, 137]);
+        assert_eq!(bytes(decoded.pixels[1]), [0, 255, 0]);
+
+        // damaged and unsupported files are errors
+        let invalid = |png: &[u8]| matches!(decode(png), Err(Error::Invalid(_)));
+        let mut corrupt = written.clone();
+        corrupt[45] ^= 0x10;
+        assert!(invalid(&corrupt));
+        assert!(invalid(&written[..written.len() - 20]));
+        assert!(invalid(&encode(header(1, 1, 8, 2), &[], &[0, 1, 2])));
+        assert!(invalid(&encode(header(1, 1, 8, 2), &[], &[5, 1, 2, 3])));
+        assert!(invalid(&encode(header(1, 1, 4, 2), &[], &[0, 1])));
+        let mut interlaced = header(1, 1, 8, 2);
+        interlaced[12] = 1;
+        assert!(invalid(&encode(interlaced, &[], &[0, 1, 2, 3])));
+    }
+}
@@ -1,34 +1,43 @@
+use super::color;
+use super::image::{self, Error, Image};
+use super::tonemap::ToneMap;
 use super::vec3::Vec3;
 
-#[allow(dead_code)]
-pub fn p3_header(width: u16, height: u16) -> String {
+pub fn p3_header(width: u32, height: u32) -> String {
     format!("P3\n{} {}\n255\n", width, height)
 }
 
-#[allow(dead_code)]
-pub fn p3_pixel(color: &Vec3, samples_per_pixel: u32) -> String {
-    if !color.is_valid_color(samples_per_pixel) {
-        panic!("Color {} {} out of range", &color, samples_per_pixel)
-    }
+pub fn p3_pixel(color: &Vec3, samples_per_pixel: u32, tone_map: &ToneMap) -> String {
+    let c = color::display(color, samples_per_pixel, tone_map);
+    format!(
+        "{} {} {}\n",
+        color::to_u8(c.r()),
+        color::to_u8(c.g()),
+        color::to_u8(c.b())
+    )
+}
 
-    let scale = 1.0 / f64::from(samples_per_pixel);
-    let r = color.r() * scale;
-    let g = color.g() * scale;
-    let b = color.b() * scale;
-
-    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-    {
-        format!(
-            "{} {} {}\n",
-            (256.0 * r.sqrt().clamp(0.0, 0.999)) as u8,
-            (256.0 * g.sqrt().clamp(0.0, 0.999)) as u8,
-            (256.0 * b.sqrt().clamp(0.0, 0.999)) as u8,
-        )
+pub fn p3_image(
+    width: u32,
+    height: u32,
+    colors: &[Vec3],
+    samples_per_pixel: u32,
+    tone_map: &ToneMap,
+) -> Vec<u8> {
+    let mut image = p3_header(width, height);
+    for color in colors {
+        image.push_str(&p3_pixel(color, samples_per_pixel, tone_map));
     }
+    image.into_bytes()
 }
 
-#[allow(dead_code)]
-pub fn p6_image(width: u32, height: u32, colors: &[Vec3], samples_per_pixel: u32) -> Vec<u8> {
+pub fn p6_image(
+    width: u32,
+    height: u32,
+    colors: &[Vec3],
+    samples_per_pixel: u32,
+    tone_map: &ToneMap,
+) -> Vec<u8> {
     if colors.is_empty() || colors.len() != width as usize * height as usize {
         // TODO: error
         let mut image = vec![];
@@ -38,29 +47,100 @@ pub fn p6_image(width: u32, height: u32, colors: &[Vec3], samples_per_pixel: u32
     let mut image = Vec::from(format!("P6 {} {} 255\n", width, height).as_bytes());
 
     for color in colors {
-        if !color.is_valid_color(samples_per_pixel) {
-            panic!("Color {} {} out of range", &color, samples_per_pixel)
-        }
+        let c = color::display(color, samples_per_pixel, tone_map);
+        image.push(color::to_u8(c.r()));
+        image.push(color::to_u8(c.g()));
+        image.push(color::to_u8(c.b()));
+    }
+
+    image
+}
+
+// Plain (P3) or binary (P6) PPM, with any maximum value up to 65535
+pub fn decode(bytes: &[u8]) -> Result<Image, Error> {
+    let binary = bytes.starts_with(b"P6");
+    let mut position = 2;
+    let width = header_number(bytes, &mut position, "width")?;
+    let height = header_number(bytes, &mut position, "height")?;
+    let max = header_number(bytes, &mut position, "maximum value")?;
+    image::check_size(width, height)?;
+    if max == 0 || max > 0xffff {
+        return Err(Error::Invalid(format!(
+            "maximum value {} isn't from 1 to 65535",
+            max
+        )));
+    }
 
-        let scale = 1.0 / f64::from(samples_per_pixel);
-        let r = color.r() * scale;
-        let g = color.g() * scale;
-        let b = color.b() * scale;
+    let count = width * height * 3;
+    let values: Vec<usize> = if binary {
+        // one whitespace byte separates the header from the samples, which are 2 bytes big endian above 255
+        let start = position + 1;
+        let size = if max > 0xff { 2 } else { 1 };
+        let raster = match bytes.get(start..start + count * size) {
+            Some(r) => r,
+            None => return Err(Error::Invalid(String::from("PPM image data is cut short"))),
+        };
+        raster
+            .chunks(size)
+            .map(|c| c.iter().fold(0, |v, &b| v << 8 | b as usize))
+            .collect()
+    } else {
+        (0..count)
+            .map(|_| header_number(bytes, &mut position, "sample"))
+            .collect::<Result<_, _>>()?
+    };
+    if let Some(v) = values.iter().find(|&&v| v > max) {
+        return Err(Error::Invalid(format!(
+            "sample {} is above the maximum value {}",
+            v, max
+        )));
+    }
 
-        #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-        {
-            image.push((256.0 * r.sqrt().clamp(0.0, 0.999)) as u8);
-            image.push((256.0 * g.sqrt().clamp(0.0, 0.999)) as u8);
-            image.push((256.0 * b.sqrt().clamp(0.0, 0.999)) as u8);
+    #[allow(clippy::cast_precision_loss)]
+    let scale = |v: usize| v as f64 / max as f64;
+    Ok(Image {
+        width,
+        height,
+        pixels: values
+            .chunks(3)
+            .map(|c| Vec3::from_xyz(scale(c[0]), scale(c[1]), scale(c[2])))
+            .collect(),
+    })
+}
+
+// Decimal number after whitespace and # comments
+fn header_number(bytes: &[u8], position: &mut usize, what: &str) -> Result<usize, Error> {
+    while let Some(&b) = bytes.get(*position) {
+        if b == b'#' {
+            while matches!(bytes.get(*position), Some(&b) if b != b'\n') {
+                *position += 1;
+            }
+        } else if b.is_ascii_whitespace() {
+            *position += 1;
+        } else {
+            break;
         }
     }
 
-    image
+    let start = *position;
+    while matches!(bytes.get(*position), Some(b) if b.is_ascii_digit()) {
+        *position += 1;
+    }
+    // anything longer could overflow, and is far past any valid value anyway
+    if *position == start || *position - start > 9 {
+        return Err(Error::Invalid(format!(
+            "PPM {} is missing or invalid",
+            what
+        )));
+    }
+    Ok(bytes[start..*position]
+        .iter()
+        .fold(0, |v, &b| v * 10 + (b - b'0') as usize))
 }
 
 #[cfg(test)]
 mod test {
-    use super::Vec3;
+    use super::{decode, Error, ToneMap, Vec3};
 
     #[test]
     fn p3_header() {
@@ -70,8 +150,17 @@ mod test {
     #[test]
     fn p3_pixel() {
         assert_eq!(
-            super::p3_pixel(&Vec3::from_xyz(0.0, 0.77, 1.0), 1),
-            "0 224 255\n"
+            super::p3_pixel(&Vec3::from_xyz(0.0, 0.77, 1.0), 1, &ToneMap::default()),
+            "0 228 255\n"
+        );
+    }
+
+    #[test]
+    fn p3_image() {
+        let colors = vec![Vec3::from_xyz(4.0, 0.0, 0.0), Vec3::from_xyz(0.0, 0.0, 1.0)];
+        assert_eq!(
+            super::p3_image(2, 1, &colors, 4, &ToneMap::default()),
+            b"P3\n2 1\n255\n255 0 0\n0 0 137\n"
         );
     }
 
@@ -86,11 +175,38 @@ mod test {
             Vec3::from_xyz(0.0, 0.0, 0.5),
         ];
         assert_eq!(
-            super::p6_image(3, 2, &colors, 1),
+            super::p6_image(3, 2, &colors, 1, &ToneMap::default()),
             [
-                80, 54, 32, 51, 32, 50, 32, 50, 53, 53, 10, 255, 0, 0, 0, 255, 0, 0, 0, 255, 181,
-                0, 0, 0, 181, 0, 0, 0, 181
+                80, 54, 32, 51, 32, 50, 32, 50, 53, 53, 10, 255, 0, 0, 0, 255, 0, 0, 0, 255, 188,
+                0, 0, 0, 188, 0, 0, 0, 188
             ]
         );
     }
+
+    #[test]
+    fn decode_ppm() {
+        let image = decode(b"P3\n# a comment\n2 1 # another\n15\n15 0 0  0 3 15\n").unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels[0], Vec3::from_xyz(1.0, 0.0, 0.0));
+        assert_eq!(image.pixels[1], Vec3::from_xyz(0.0, 0.2, 1.0));
+
+        let image = decode(b"P6 1 2 255\n\xff\x00\x33\x00\x00\x00").unwrap();
+        assert_eq!(image.pixel(0, 0), Vec3::from_xyz(1.0, 0.0, 0.2));
+        assert_eq!(image.pixel(0, 1), Vec3::new());
+
+        // 16-bit samples
+        let image = decode(b"P6 1 1 65535\n\xff\xff\x80\x00\x00\x00").unwrap();
+        assert!((image.pixels[0].g() - 32768.0 / 65535.0).abs() < 1e-12);
+
+        // a written image reads back the same
+        let written = super::p6_image(2, 1, &image.pixels.repeat(2), 1, &ToneMap::default());
+        assert_eq!(decode(&written).unwrap().width, 2);
+
+        let invalid = |bytes: &[u8]| matches!(decode(bytes), Err(Error::Invalid(_)));
+        assert!(invalid(b"P6 2 2 255\n\x00\x00"));
+        assert!(invalid(b"P3 1 1 255 0 0"));
+        assert!(invalid(b"P3 1 1 255 0 256 0"));
+        assert!(invalid(b"P3 1 1 0 0 0 0"));
+        assert!(invalid(b"P3 1 x 255"));
+    }
 }
@@ -1,8 +1,15 @@
-use super::hit::Hittable;
+use super::environment::EnvironmentWritable;
+use super::hit::{Hit, Hittable};
+use super::material::Scatter;
 use super::vec3::Vec3;
+use super::world::World;
+use rand::Rng;
 use rand_pcg::Pcg64Mcg;
 
-#[derive(Debug, PartialEq)]
+// Bounces every path makes before Russian roulette can end it
+const ROULETTE_BOUNCES: u16 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
@@ -23,21 +30,146 @@ impl Ray {
         self.origin + self.direction * t
     }
 
-    pub fn color<T: Hittable>(&self, hittable: &T, mut rng: &mut Pcg64Mcg, depth: u16) -> Vec3 {
-        if depth == 0 {
-            return Vec3::default();
-        }
+    // Light along the ray, followed through up to max_depth bounces. The path is traced in a loop carrying the
+    // product of every bounce's weight so far (its throughput), and once it's had a few bounces it's ended at random
+    // with probability based on how little that throughput is, with the paths that carry on weighted up to make up for
+    // it (Russian roulette). Where materials sample their scattering the lights are sampled as well, and the two are
+    // weighted by the balance heuristic (multiple importance sampling) so light isn't counted twice.
+    pub fn color(
+        &self,
+        world: &World,
+        background: &dyn EnvironmentWritable,
+        rng: &mut Pcg64Mcg,
+        max_depth: u16,
+    ) -> Vec3 {
+        let lights = Lights { world, background };
+        let mut ray = *self;
+        let mut radiance = Vec3::new();
+        let mut throughput = Vec3::from_xyz(1.0, 1.0, 1.0);
+        // scales emitted light found by the latest bounce, which is less than 1 when lights were also sampled
+        let mut emission_weight = 1.0;
+
+        for bounce in 0..max_depth {
+            let hit = if let Some(hit) = world.hit(&ray, 0.001, f64::INFINITY, rng) {
+                hit
+            } else {
+                radiance += throughput * background.color(ray.direction) * emission_weight;
+                break;
+            };
+            radiance +=
+                throughput * hit.material.emitted(hit.u, hit.v, hit.point) * emission_weight;
 
-        if let Some(hit) = hittable.hit(self, 0.001, f64::INFINITY) {
-            if let Some(scatter) = hit.material.scatter(self, &mut rng, &hit) {
-                return scatter.attenuation * scatter.ray.color(hittable, &mut rng, depth - 1);
+            let (scattered, weight) = match hit.material.scatter(&ray, rng, &hit) {
+                None => break,
+                Some(Scatter::Specular { ray, attenuation }) => {
+                    emission_weight = 1.0;
+                    (ray, attenuation)
+                }
+                Some(Scatter::Sampled {
+                    ray: scattered,
+                    pdf,
+                }) => {
+                    if pdf <= 0.0 {
+                        break;
+                    }
+                    // light from the next hit is only counted while there's depth left for it
+                    emission_weight = if lights.any() && bounce + 1 < max_depth {
+                        radiance += throughput * ray.sample_light(&lights, rng, &hit);
+                        let light_pdf = lights.pdf(hit.point, scattered.direction, ray.time);
+                        pdf / (pdf + light_pdf)
+                    } else {
+                        1.0
+                    };
+                    // the Monte Carlo estimate of the light scattered from the sampled direction
+                    let weight = hit.material.scattering(&ray, &hit, scattered.direction) / pdf;
+                    (scattered, weight)
+                }
+            };
+            throughput = throughput * weight;
+
+            if bounce + 1 >= ROULETTE_BOUNCES {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(1.0);
+                if rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput /= survival;
             }
+            ray = scattered;
+        }
+
+        radiance
+    }
+
+    // Light reaching the hit straight from a point picked on one of the lights or a direction picked towards the
+    // background, if nothing is in the way
+    fn sample_light(&self, lights: &Lights, rng: &mut Pcg64Mcg, hit: &Hit) -> Vec3 {
+        let direction = match lights.direction(hit.point, rng) {
+            Some(direction) => direction,
+            None => return Vec3::new(),
+        };
+        let scattering = hit.material.scattering(self, hit, direction);
+        let light_pdf = lights.pdf(hit.point, direction, self.time);
+        if scattering == Vec3::new() || light_pdf <= 0.0 {
             return Vec3::new();
         }
 
-        let unit_direction = self.direction.unit_vector();
-        let t = 0.5 * (unit_direction.y + 1.0);
-        Vec3::from_xyz(1.0, 1.0, 1.0) * (1.0 - t) + Vec3::from_xyz(0.5, 0.7, 1.0) * t
+        let shadow = Ray {
+            origin: hit.point,
+            direction,
+            time: self.time,
+        };
+        // whatever's hit first is what's seen, the light itself or something emitting in front of it
+        let emitted = match lights.world.hit(&shadow, 0.001, f64::INFINITY, rng) {
+            Some(light) => light.material.emitted(light.u, light.v, light.point),
+            None => lights.background.color(direction),
+        };
+        // scattering / light_pdf weighted by light_pdf over the combined density of both ways of finding this
+        // direction
+        let scatter_pdf = hit.material.scattering_pdf(self, hit, direction);
+        scattering * emitted / (scatter_pdf + light_pdf)
+    }
+}
+
+// Everything sampled directly: the scene's lights, and the background when it can be
+struct Lights<'a> {
+    world: &'a World,
+    background: &'a dyn EnvironmentWritable,
+}
+
+impl<'a> Lights<'a> {
+    fn any(&self) -> bool {
+        self.world.has_lights() || self.background.is_sampled()
+    }
+
+    // Chance of a direction being picked towards the background rather than the scene's lights, half when
+    // there are both
+    fn background_chance(&self) -> f64 {
+        match (self.world.has_lights(), self.background.is_sampled()) {
+            (_, false) => 0.0,
+            (false, true) => 1.0,
+            (true, true) => 0.5,
+        }
+    }
+
+    fn direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        if rng.gen::<f64>() < self.background_chance() {
+            self.background.random_direction(rng)
+        } else {
+            self.world.light_direction(origin, rng)
+        }
+    }
+
+    // Density over solid angle of direction picking direction
+    fn pdf(&self, origin: Vec3, direction: Vec3, time: f64) -> f64 {
+        let chance = self.background_chance();
+        let mut pdf = 0.0;
+        if chance > 0.0 {
+            pdf += chance * self.background.pdf_value(direction);
+        }
+        if chance < 1.0 {
+            pdf += (1.0 - chance) * self.world.light_pdf(origin, direction, time);
+        }
+        pdf
     }
 }
 
@@ -49,7 +181,18 @@ impl Default for Ray {
 
 #[cfg(test)]
 mod test {
-    use super::{Ray, Vec3};
+    use super::{Hit, Ray, Scatter, Vec3, World};
+    use crate::bvh::Split;
+    use crate::environment::{Environment, EnvironmentMap, EnvironmentWritable, Solid};
+    use crate::hit::Hittable;
+    use crate::image::Image;
+    use crate::material::{DiffuseLight, Lambertian, Material, MaterialWritable};
+    use crate::plane::Plane;
+    use crate::rect::XZRect;
+    use crate::sphere::Sphere;
+    use crate::texture::SolidColor;
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
 
     #[test]
     fn at() {
@@ -61,4 +204,210 @@ mod test {
         let b = &a.at(2.5);
         assert_eq!(b, &Vec3::from_xyz(2.5, 4.0, 5.5));
     }
+
+    #[test]
+    fn light_sampling() {
+        let emit = || {
+            Arc::new(DiffuseLight::new(Box::new(SolidColor::from_rgb(
+                10.0, 10.0, 10.0,
+            ))))
+        };
+        let lights: Vec<Arc<dyn Hittable>> = vec![
+            Arc::new(XZRect {
+                x0: -0.5,
+                x1: 0.5,
+                z0: -0.5,
+                z1: 0.5,
+                k: 2.0,
+                material: emit(),
+            }),
+            Arc::new(Sphere {
+                center: Vec3::from_xyz(0.0, 2.0, 0.0),
+                radius: 0.5,
+                material: emit(),
+            }),
+        ];
+        // down onto the floor right under the light
+        let r = Ray {
+            origin: Vec3::from_xyz(0.0, 1.0, 3.0),
+            direction: Vec3::from_xyz(0.0, -1.0, -3.0),
+            time: 0.0,
+        };
+
+        for light in lights {
+            let world = |lights: Vec<Arc<dyn Hittable>>| {
+                let floor = Plane::new(
+                    Vec3::new(),
+                    Vec3::from_xyz(0.0, 1.0, 0.0),
+                    Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                        0.5, 0.5, 0.5,
+                    )))),
+                )
+                .unwrap();
+                World::new(
+                    &mut Pcg64Mcg::new(1),
+                    vec![Arc::new(floor), Arc::clone(&light)],
+                    lights,
+                    0.0,
+                    1.0,
+                    Split::SurfaceArea,
+                )
+            };
+            // the same light found only by scattered rays, and by sampling it as well
+            let (hit_mean, hit_deviation) = estimate(&world(vec![]), &Solid(Vec3::new()), &r);
+            let (sampled_mean, sampled_deviation) =
+                estimate(&world(vec![Arc::clone(&light)]), &Solid(Vec3::new()), &r);
+
+            assert!((sampled_mean - hit_mean).abs() < 0.05 * hit_mean);
+            assert!(sampled_deviation < hit_deviation / 10.0);
+        }
+    }
+
+    // Lambertian that also glows, so light builds up over every bounce inside a closed shape
+    #[derive(Debug)]
+    struct Glowing(Lambertian);
+
+    impl Material for Glowing {
+        fn scatter(&self, r_in: &Ray, rng: &mut Pcg64Mcg, hit: &Hit) -> Option<Scatter> {
+            self.0.scatter(r_in, rng, hit)
+        }
+
+        fn scattering(&self, r_in: &Ray, hit: &Hit, direction: Vec3) -> Vec3 {
+            self.0.scattering(r_in, hit, direction)
+        }
+
+        fn emitted(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+            Vec3::from_xyz(1.0, 1.0, 1.0)
+        }
+    }
+
+    impl MaterialWritable for Glowing {}
+
+    #[test]
+    fn russian_roulette() {
+        // inside a sphere emitting 1 and reflecting half, every bounce adds half as much again, so the radiance
+        // approaches 2 with depth
+        let sphere = Sphere {
+            center: Vec3::new(),
+            radius: 1.0,
+            material: Arc::new(Glowing(Lambertian::new(Box::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            ))))),
+        };
+        let world = World::new(
+            &mut Pcg64Mcg::new(1),
+            vec![Arc::new(sphere)],
+            vec![],
+            0.0,
+            1.0,
+            Split::SurfaceArea,
+        );
+        let r = Ray {
+            origin: Vec3::new(),
+            direction: Vec3::from_xyz(0.3, -0.2, 1.0),
+            time: 0.0,
+        };
+
+        let mut rng = Pcg64Mcg::new(3);
+        let background = Solid(Vec3::new());
+        // far more depth than a recursive tracer's stack could take, the paths end by roulette long before it
+        let total = (0..40_000).fold(Vec3::new(), |total, _| {
+            total + r.color(&world, &background, &mut rng, u16::MAX)
+        });
+        assert!(((total / 40_000.0).x - 2.0).abs() < 0.02);
+
+        // with too little depth for roulette, the light from the bounces past it is missed
+        let shallow = r.color(&world, &background, &mut rng, 2);
+        assert_eq!(shallow, Vec3::from_xyz(1.5, 1.5, 1.5));
+    }
+
+    // Only the colors of an environment, so it's found by scattered rays alone
+    #[derive(Debug)]
+    struct Unsampled(EnvironmentMap);
+
+    impl Environment for Unsampled {
+        fn color(&self, direction: Vec3) -> Vec3 {
+            self.0.color(direction)
+        }
+    }
+
+    impl EnvironmentWritable for Unsampled {}
+
+    #[test]
+    fn environment_sampling() {
+        // dim but for a bright patch, high up on one side
+        let map = || {
+            let (width, height) = (32, 16);
+            let mut pixels = vec![Vec3::from_xyz(0.2, 0.2, 0.2); width * height];
+            for y in 2..5 {
+                for x in 20..23 {
+                    pixels[y * width + x] = Vec3::from_xyz(100.0, 100.0, 100.0);
+                }
+            }
+            EnvironmentMap::new(
+                Image {
+                    width,
+                    height,
+                    pixels,
+                },
+                0.0,
+                1.0,
+            )
+        };
+        let r = Ray {
+            origin: Vec3::from_xyz(0.0, 1.0, 3.0),
+            direction: Vec3::from_xyz(0.0, -1.0, -3.0),
+            time: 0.0,
+        };
+        let floor = || -> Arc<dyn Hittable> {
+            Arc::new(
+                Plane::new(
+                    Vec3::new(),
+                    Vec3::from_xyz(0.0, 1.0, 0.0),
+                    Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                        0.5, 0.5, 0.5,
+                    )))),
+                )
+                .unwrap(),
+            )
+        };
+        let light: Arc<dyn Hittable> = Arc::new(XZRect {
+            x0: -0.5,
+            x1: 0.5,
+            z0: -0.5,
+            z1: 0.5,
+            k: 2.0,
+            material: Arc::new(DiffuseLight::new(Box::new(SolidColor::from_rgb(
+                10.0, 10.0, 10.0,
+            )))),
+        });
+
+        // on its own, and sharing the light samples with a light in the scene
+        for lights in vec![vec![], vec![light]] {
+            let world = World::new(
+                &mut Pcg64Mcg::new(1),
+                lights.iter().cloned().chain(Some(floor())).collect(),
+                lights,
+                0.0,
+                1.0,
+                Split::SurfaceArea,
+            );
+            let (hit_mean, hit_deviation) = estimate(&world, &Unsampled(map()), &r);
+            let (sampled_mean, sampled_deviation) = estimate(&world, &map(), &r);
+
+            assert!((sampled_mean - hit_mean).abs() < 0.05 * hit_mean);
+            assert!(sampled_deviation < hit_deviation / 3.0);
+        }
+    }
+
+    // The mean and standard deviation of a ray's red radiance
+    fn estimate(world: &World, background: &dyn EnvironmentWritable, r: &Ray) -> (f64, f64) {
+        let mut rng = Pcg64Mcg::new(2);
+        let samples: Vec<f64> = (0..40_000)
+            .map(|_| r.color(world, background, &mut rng, 5).x)
+            .collect();
+        let mean = samples.iter().sum::<f64>() / 40_000.0;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / 40_000.0;
+        (mean, variance.sqrt())
+    }
 }
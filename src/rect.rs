@@ -0,0 +1,341 @@
+use super::aabb::AABB;
+use super::hit::{Hit, Hittable};
+use super::material::MaterialWritable;
+use super::ray::Ray;
+use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// Rectangles have no thickness, so pad their bounding boxes along the flat axis to keep the BVH happy
+const PADDING: f64 = 0.0001;
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub struct XYRect {
+    pub x0: f64,
+    pub x1: f64,
+    pub y0: f64,
+    pub y1: f64,
+    pub k: f64,
+    pub material: Arc<dyn MaterialWritable>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub struct XZRect {
+    pub x0: f64,
+    pub x1: f64,
+    pub z0: f64,
+    pub z1: f64,
+    pub k: f64,
+    pub material: Arc<dyn MaterialWritable>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub struct YZRect {
+    pub y0: f64,
+    pub y1: f64,
+    pub z0: f64,
+    pub z1: f64,
+    pub k: f64,
+    pub material: Arc<dyn MaterialWritable>,
+}
+
+// Shared intersection for a rectangle spanning axes a and b, lying in the plane where axis k_axis == k
+#[allow(clippy::too_many_arguments)]
+fn hit_rect(
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+    (a_axis, b_axis, k_axis): (u8, u8, u8),
+    (a0, a1, b0, b1, k): (f64, f64, f64, f64, f64),
+    outward_normal: Vec3,
+    material: &Arc<dyn MaterialWritable>,
+) -> Option<Hit> {
+    let t = (k - r.origin[k_axis]) / r.direction[k_axis];
+    if !(t > t_min && t < t_max) {
+        // also rejects NaN from rays parallel to the rectangle
+        return None;
+    }
+
+    let point = r.at(t);
+    let a_coord = point[a_axis];
+    let b_coord = point[b_axis];
+    if a_coord < a0 || a_coord > a1 || b_coord < b0 || b_coord > b1 {
+        return None;
+    }
+
+    let front_face = r.direction.dot(outward_normal) < 0.0;
+    Some(Hit {
+        point,
+        normal: if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        },
+        t,
+        u: (a_coord - a0) / (a1 - a0),
+        v: (b_coord - b0) / (b1 - b0),
+        front_face,
+        color: None,
+        material: Arc::clone(material),
+    })
+}
+
+// A random point in a rectangle spanning a0 to a1 and b0 to b1 along axes a and b, where axis k == k
+fn random_point(
+    rng: &mut Pcg64Mcg,
+    (a_axis, b_axis, k_axis): (u8, u8, u8),
+    (a0, a1, b0, b1, k): (f64, f64, f64, f64, f64),
+) -> Vec3 {
+    let mut point = Vec3::new();
+    point[a_axis] = (a1 - a0).mul_add(rng.gen(), a0);
+    point[b_axis] = (b1 - b0).mul_add(rng.gen(), b0);
+    point[k_axis] = k;
+    point
+}
+
+// Density over solid angle of a rectangle sampled evenly by area, for the direction a ray hit it from
+fn area_pdf(hit: Option<Hit>, direction: Vec3, area: f64) -> f64 {
+    hit.map_or(0.0, |hit| {
+        let distance_squared = hit.t * hit.t * direction.length_squared();
+        let cosine = (direction.dot(hit.normal) / direction.length()).abs();
+        distance_squared / (cosine * area)
+    })
+}
+
+impl XYRect {
+    fn intersect(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        hit_rect(
+            r,
+            t_min,
+            t_max,
+            (0, 1, 2),
+            (self.x0, self.x1, self.y0, self.y1, self.k),
+            Vec3::from_xyz(0.0, 0.0, 1.0),
+            &self.material,
+        )
+    }
+}
+
+impl Hittable for XYRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
+        self.intersect(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<AABB> {
+        Some(AABB {
+            min: Vec3::from_xyz(self.x0, self.y0, self.k - PADDING),
+            max: Vec3::from_xyz(self.x1, self.y1, self.k + PADDING),
+        })
+    }
+
+    fn is_sampled(&self) -> bool {
+        true
+    }
+
+    fn random_direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        Some(random_point(rng, (0, 1, 2), (self.x0, self.x1, self.y0, self.y1, self.k)) - origin)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: f64) -> f64 {
+        let r = Ray {
+            origin,
+            direction,
+            time,
+        };
+        area_pdf(
+            self.intersect(&r, 0.001, f64::INFINITY),
+            direction,
+            (self.x1 - self.x0) * (self.y1 - self.y0),
+        )
+    }
+}
+
+impl XZRect {
+    fn intersect(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        hit_rect(
+            r,
+            t_min,
+            t_max,
+            (0, 2, 1),
+            (self.x0, self.x1, self.z0, self.z1, self.k),
+            Vec3::from_xyz(0.0, 1.0, 0.0),
+            &self.material,
+        )
+    }
+}
+
+impl Hittable for XZRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
+        self.intersect(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<AABB> {
+        Some(AABB {
+            min: Vec3::from_xyz(self.x0, self.k - PADDING, self.z0),
+            max: Vec3::from_xyz(self.x1, self.k + PADDING, self.z1),
+        })
+    }
+
+    fn is_sampled(&self) -> bool {
+        true
+    }
+
+    fn random_direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        Some(random_point(rng, (0, 2, 1), (self.x0, self.x1, self.z0, self.z1, self.k)) - origin)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: f64) -> f64 {
+        let r = Ray {
+            origin,
+            direction,
+            time,
+        };
+        area_pdf(
+            self.intersect(&r, 0.001, f64::INFINITY),
+            direction,
+            (self.x1 - self.x0) * (self.z1 - self.z0),
+        )
+    }
+}
+
+impl YZRect {
+    fn intersect(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        hit_rect(
+            r,
+            t_min,
+            t_max,
+            (1, 2, 0),
+            (self.y0, self.y1, self.z0, self.z1, self.k),
+            Vec3::from_xyz(1.0, 0.0, 0.0),
+            &self.material,
+        )
+    }
+}
+
+impl Hittable for YZRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
+        self.intersect(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<AABB> {
+        Some(AABB {
+            min: Vec3::from_xyz(self.k - PADDING, self.y0, self.z0),
+            max: Vec3::from_xyz(self.k + PADDING, self.y1, self.z1),
+        })
+    }
+
+    fn is_sampled(&self) -> bool {
+        true
+    }
+
+    fn random_direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        Some(random_point(rng, (1, 2, 0), (self.y0, self.y1, self.z0, self.z1, self.k)) - origin)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: f64) -> f64 {
+        let r = Ray {
+            origin,
+            direction,
+            time,
+        };
+        area_pdf(
+            self.intersect(&r, 0.001, f64::INFINITY),
+            direction,
+            (self.y1 - self.y0) * (self.z1 - self.z0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Arc, Hit, Hittable, Ray, Vec3, XZRect};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
+        let rect = XZRect {
+            x0: -1.0,
+            x1: 1.0,
+            z0: 0.0,
+            z1: 4.0,
+            k: 2.0,
+            material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        };
+        let down_ray = Ray {
+            origin: Vec3::from_xyz(0.5, 4.0, 1.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let up_ray = Ray {
+            origin: Vec3::from_xyz(0.5, 0.0, 1.0),
+            direction: Vec3::from_xyz(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let parallel_ray = Ray {
+            origin: Vec3::from_xyz(0.0, 2.0, -1.0),
+            direction: Vec3::from_xyz(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let miss_ray = Ray {
+            origin: Vec3::from_xyz(1.5, 4.0, 1.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert_eq!(
+            rect.hit(&down_ray, 0.0, 10.0, &mut rng),
+            Some(Hit {
+                point: Vec3::from_xyz(0.5, 2.0, 1.0),
+                normal: Vec3::from_xyz(0.0, 1.0, 0.0),
+                t: 2.0,
+                u: 0.75,
+                v: 0.25,
+                front_face: true,
+                color: None,
+                material: rect.material.clone(),
+            })
+        );
+        assert_eq!(
+            rect.hit(&up_ray, 0.0, 10.0, &mut rng),
+            Some(Hit {
+                point: Vec3::from_xyz(0.5, 2.0, 1.0),
+                normal: Vec3::from_xyz(0.0, -1.0, 0.0),
+                t: 2.0,
+                u: 0.75,
+                v: 0.25,
+                front_face: false,
+                color: None,
+                material: rect.material.clone(),
+            })
+        );
+        assert_eq!(rect.hit(&down_ray, 0.0, 1.0, &mut rng), None);
+        assert_eq!(rect.hit(&parallel_ray, 0.0, 10.0, &mut rng), None);
+        assert_eq!(rect.hit(&miss_ray, 0.0, 10.0, &mut rng), None);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let rect = XZRect {
+            x0: -1.0,
+            x1: 1.0,
+            z0: 0.0,
+            z1: 4.0,
+            k: 2.0,
+            material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        };
+        let bb = rect.bounding_box(0.0, 1.0).unwrap();
+        assert!(bb.max.y > bb.min.y);
+        assert_eq!(bb.min, Vec3::from_xyz(-1.0, 1.9999, 0.0));
+        assert_eq!(bb.max, Vec3::from_xyz(1.0, 2.0001, 4.0));
+    }
+}
@@ -0,0 +1,232 @@
+use super::scene::Scene;
+use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+// Renders an image in square tiles handed out to a pool of threads as each finishes its last one
+
+pub const TILE_SIZE: u32 = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u16,
+    pub seed: u128,
+}
+
+impl Settings {
+    fn tiles_across(&self) -> u32 {
+        (self.width + self.tile_size - 1) / self.tile_size
+    }
+
+    fn tile_count(&self) -> usize {
+        self.tiles_across() as usize
+            * ((self.height + self.tile_size - 1) / self.tile_size) as usize
+    }
+}
+
+// Pixel columns and rows (from the top of the image) covered by a tile, the last ones in a row or column get cut short
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Tile {
+    x0: u32,
+    x1: u32,
+    y0: u32,
+    y1: u32,
+}
+
+impl Tile {
+    fn new(settings: &Settings, index: usize) -> Self {
+        #[allow(clippy::cast_possible_truncation)]
+        let index = index as u32;
+        let x0 = index % settings.tiles_across() * settings.tile_size;
+        let y0 = index / settings.tiles_across() * settings.tile_size;
+        Self {
+            x0,
+            x1: (x0 + settings.tile_size).min(settings.width),
+            y0,
+            y1: (y0 + settings.tile_size).min(settings.height),
+        }
+    }
+}
+
+// Sums of every pixel's samples in rows from the top of the image, to be divided by samples_per_pixel when written out.
+// on_progress is called with the number of finished tiles and the total after each tile comes back.
+pub fn render<F: FnMut(usize, usize)>(
+    scene: &Arc<Scene>,
+    settings: Settings,
+    threads: u32,
+    mut on_progress: F,
+) -> Vec<Vec3> {
+    let tile_count = settings.tile_count();
+    let next_tile = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let scene = Arc::clone(scene);
+            let next_tile = Arc::clone(&next_tile);
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                // claim the next tile nobody has started, until there are none left
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                if index >= tile_count {
+                    break;
+                }
+                let tile = Tile::new(&settings, index);
+                let colors = render_tile(&scene, &settings, tile, index);
+                if sender.send((tile, colors)).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect();
+    // the receiver hangs up once every worker's sender is dropped
+    drop(sender);
+
+    let width = settings.width as usize;
+    let mut image = vec![Vec3::new(); width * settings.height as usize];
+    for (finished, (tile, colors)) in receiver.iter().enumerate() {
+        let tile_width = (tile.x1 - tile.x0) as usize;
+        for (row, tile_row) in (tile.y0 as usize..tile.y1 as usize).zip(colors.chunks(tile_width)) {
+            let start = row * width + tile.x0 as usize;
+            image[start..start + tile_width].copy_from_slice(tile_row);
+        }
+        on_progress(finished + 1, tile_count);
+    }
+
+    for handle in handles {
+        handle.join().expect("render thread panicked");
+    }
+
+    image
+}
+
+// Each tile gets its own random stream from the seed and its index, so the image doesn't depend on the number of
+// threads or which of them rendered which tile
+fn tile_rng(seed: u128, index: usize) -> Pcg64Mcg {
+    // multiplying spreads consecutive seeds and indices across all the bits, Pcg64Mcg::new sets the lowest two
+    // bits so seeds that only differ there would otherwise give the same stream
+    const SPREAD: u128 = 0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c835;
+    let offset = (index as u128 + 1).wrapping_mul(SPREAD);
+    Pcg64Mcg::new((seed ^ offset).wrapping_mul(SPREAD))
+}
+
+fn render_tile(scene: &Scene, settings: &Settings, tile: Tile, index: usize) -> Vec<Vec3> {
+    let mut rng = tile_rng(settings.seed, index);
+    let mut colors = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+
+    for row in tile.y0..tile.y1 {
+        let j = settings.height - 1 - row;
+        for i in tile.x0..tile.x1 {
+            let mut pixel_color = Vec3::new();
+            for _ in 0..settings.samples_per_pixel {
+                let u = (f64::from(i) + rng.gen_range(0.0, 1.0)) / f64::from(settings.width - 1);
+                let v = (f64::from(j) + rng.gen_range(0.0, 1.0)) / f64::from(settings.height - 1);
+                let r = scene.camera.ray(&mut rng, u, v);
+                pixel_color += r.color(
+                    &scene.world,
+                    &*scene.background,
+                    &mut rng,
+                    settings.max_depth,
+                );
+            }
+            colors.push(pixel_color);
+        }
+    }
+
+    colors
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, Arc, Settings, Tile};
+    use crate::bvh::Split;
+    use crate::scene;
+    use std::path::Path;
+
+    #[test]
+    fn tiles() {
+        let settings = Settings {
+            width: 10,
+            height: 5,
+            tile_size: 4,
+            samples_per_pixel: 1,
+            max_depth: 1,
+            seed: 0,
+        };
+        assert_eq!(settings.tile_count(), 6);
+        assert_eq!(
+            Tile::new(&settings, 2),
+            Tile {
+                x0: 8,
+                x1: 10,
+                y0: 0,
+                y1: 4
+            }
+        );
+        assert_eq!(
+            Tile::new(&settings, 4),
+            Tile {
+                x0: 4,
+                x1: 8,
+                y0: 4,
+                y1: 5
+            }
+        );
+    }
+
+    #[test]
+    fn deterministic() {
+        let scene = Arc::new(
+            scene::parse(
+                "camera from 0 1 5 at 0 0 0 vfov 40
+material red lambertian 0.8 0.1 0.1
+material mirror metal 0.9 0.9 0.9 0.1
+sphere red 0 0 0 1
+sphere mirror 1.5 0 -1 0.5",
+                Path::new(""),
+                2.0,
+                1,
+                Split::SurfaceArea,
+            )
+            .unwrap(),
+        );
+        let settings = Settings {
+            width: 18,
+            height: 9,
+            tile_size: 4,
+            samples_per_pixel: 3,
+            max_depth: 4,
+            seed: 42,
+        };
+
+        let mut progress = vec![];
+        let one = render(&scene, settings, 1, |done, total| {
+            progress.push((done, total))
+        });
+        assert_eq!(progress.len(), 15);
+        assert_eq!(progress.last(), Some(&(15, 15)));
+
+        let many = render(&scene, settings, 4, |_, _| {});
+        assert_eq!(one, many);
+        assert!(one.iter().any(|c| c.r() > 0.0));
+
+        let reseeded = render(
+            &scene,
+            Settings {
+                seed: 43,
+                ..settings
+            },
+            4,
+            |_, _| {},
+        );
+        assert_ne!(one, reseeded);
+    }
+}
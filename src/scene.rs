@@ -0,0 +1,1335 @@
+use super::bvh::{FlatBVH, Split};
+use super::camera::Camera;
+use super::constant_medium::ConstantMedium;
+use super::cuboid::Cuboid;
+use super::daylight::Daylight;
+use super::environment::{EnvironmentMap, EnvironmentWritable, Gradient, Solid};
+use super::hit::Hittable;
+use super::image::{self, Image};
+use super::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, MaterialWritable, Metal};
+use super::matrix::Matrix4;
+use super::moving_sphere::MovingSphere;
+use super::obj;
+use super::perlin::Perlin;
+use super::plane::Plane;
+use super::ply;
+use super::rect::{XYRect, XZRect, YZRect};
+use super::sphere::Sphere;
+use super::texture::{
+    Addressing, CheckerTexture, Filter, ImageTexture, NoiseStyle, NoiseTexture, SolidColor,
+    TextureWritable, VertexColorTexture,
+};
+use super::transform::Transform;
+use super::triangle::Triangle;
+use super::vec3::Vec3;
+use super::world::World;
+use rand_pcg::Pcg64Mcg;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Statements that make one object from a material and its arguments
+const SHAPES: [&str; 8] = [
+    "sphere",
+    "moving_sphere",
+    "xy_rect",
+    "xz_rect",
+    "yz_rect",
+    "box",
+    "triangle",
+    "plane",
+];
+
+// Everything needed to render an image: where it's viewed from, what's in it, and what's behind it
+pub struct Scene {
+    pub camera: Camera,
+    pub world: World,
+    pub background: Box<dyn EnvironmentWritable>,
+}
+
+// Scene files are one statement per line, # starts a comment, and paths with spaces can be quoted
+//
+//   camera from 13 2 3 at 0 0 0 up 0 1 0 vfov 20 aperture 0.1 focus 10 time 0 1
+//   background sky | background color 0 0 0
+//   background map <path> [rotate <degrees>] [intensity <scale>] [linear | srgb]
+//   background daylight <sun direction> [turbidity <2 to 10>] [albedo <r g b>] [intensity <scale>]
+//
+//   texture <name> solid <r g b>
+//   texture <name> checker <even texture> <odd texture>
+//   texture <name> noise <scale> [smooth | turbulence | marble]
+//   texture <name> image <path> [nearest | bilinear] [wrap | clamp | mirror] [srgb | linear]
+//   texture <name> vertex_color
+//
+//   material <name> lambertian <texture | r g b>
+//   material <name> metal <r g b> <fuzz>
+//   material <name> dielectric <refraction index>
+//   material <name> diffuse_light <texture | r g b>
+//
+//   sphere <material> <center> <radius>
+//   moving_sphere <material> <center0> <center1> <time0> <time1> <radius>
+//   xy_rect <material> <x0> <x1> <y0> <y1> <z>
+//   xz_rect <material> <x0> <x1> <z0> <z1> <y>
+//   yz_rect <material> <y0> <y1> <z0> <z1> <x>
+//   box <material> <min> <max>
+//   triangle <material> <v0> <v1> <v2>
+//   plane <material> <point> <normal>
+//   obj <material> <path>
+//   ply <material> <path>
+//   medium <texture | r g b> <density> <boundary shape and its arguments, without a material>
+//
+// Spheres and rectangles made of diffuse_light are sampled directly as lights from diffuse surfaces, unless
+// they're transformed. Other shapes still give off light but are only found by rays that happen to hit them.
+//
+// A background map is a latitude-longitude image around the scene, usually HDR, with its middle looking down -z
+// before it's rotated about the y axis. It's sampled as a light too, picking directions by how bright they are.
+// A daylight background is a clear sky lit by the sun from its direction, which is sampled as a light, over ground
+// of the albedo's color (0.3 gray when left out); turbidity 3 is a clear day.
+//
+// A vertex_color texture blends the colors at the corners of the triangle hit, for ply files that have them, and
+// is white on anything else
+//
+// A medium fills a convex shape with smoke or fog, scattering the light inside equally in every direction and
+// tinting it by the texture
+//
+//   transform [translate <x y z> | rotate <axis x y z> <degrees> | scale <x y z> | scale <s> | matrix <16 numbers>]...
+//
+// Every object after a transform statement is placed by it, until the next one (transform on its own goes back
+// to placing objects as they are). Its steps are applied to objects in the order they're written, and a matrix is
+// given row by row. Each mesh file is only loaded once, however many times it's placed.
+//
+// Names must be declared before they're used, camera settings left out use the defaults in CameraDef
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    // A mesh or image referenced by the scene couldn't be loaded
+    Load {
+        line: usize,
+        column: usize,
+        error: Box<dyn std::error::Error>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            Self::Load {
+                line,
+                column,
+                error,
+            } => write!(f, "line {}, column {}: {}", line, column, error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+// Relative paths in the file are resolved from the file's directory
+pub fn load(
+    path: &Path,
+    aspect_ratio: f64,
+    scene_seed: u128,
+    split: Split,
+) -> Result<Scene, Error> {
+    parse(
+        &fs::read_to_string(path)?,
+        path.parent().unwrap_or_else(|| Path::new("")),
+        aspect_ratio,
+        scene_seed,
+        split,
+    )
+}
+
+pub fn parse(
+    source: &str,
+    dir: &Path,
+    aspect_ratio: f64,
+    scene_seed: u128,
+    split: Split,
+) -> Result<Scene, Error> {
+    let mut builder = Builder {
+        dir,
+        rng: Pcg64Mcg::new(scene_seed),
+        split,
+        camera: CameraDef::default(),
+        background: Box::new(Gradient),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        transform: None,
+        meshes: HashMap::new(),
+        objects: vec![],
+        lights: vec![],
+    };
+
+    let mut last_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        last_line = i + 1;
+        let tokens = tokenize(line, i + 1)?;
+        if tokens.is_empty() {
+            continue;
+        }
+        let mut statement = Statement {
+            tokens,
+            next: 0,
+            line: i + 1,
+            end_column: line.chars().count() + 1,
+        };
+        builder.statement(&mut statement)?;
+        statement.finish()?;
+    }
+
+    if builder.objects.is_empty() {
+        return Err(Error::Parse {
+            line: last_line,
+            column: 1,
+            message: String::from("scene has no objects"),
+        });
+    }
+
+    let (time0, time1) = builder.camera.time;
+    Ok(Scene {
+        camera: builder.camera.build(aspect_ratio),
+        world: World::new(
+            &mut builder.rng,
+            builder.objects,
+            builder.lights,
+            time0,
+            time1,
+            split,
+        ),
+        background: builder.background,
+    })
+}
+
+struct Token {
+    text: String,
+    column: usize,
+}
+
+// Splits a line into whitespace separated words, keeping quoted strings together and dropping comments
+fn tokenize(line: &str, line_num: usize) -> Result<Vec<Token>, Error> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().enumerate().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '#' {
+            break;
+        }
+
+        let mut text = String::new();
+        if c == '"' {
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, c)) => text.push(c),
+                    None => {
+                        return Err(Error::Parse {
+                            line: line_num,
+                            column: i + 1,
+                            message: String::from("unterminated quote"),
+                        })
+                    }
+                }
+            }
+        } else {
+            text.push(c);
+            while let Some((_, c)) = chars.peek() {
+                if c.is_whitespace() || *c == '#' {
+                    break;
+                }
+                text.push(*c);
+                chars.next();
+            }
+        }
+        tokens.push(Token {
+            text,
+            column: i + 1,
+        });
+    }
+
+    Ok(tokens)
+}
+
+// Cursor over one line's tokens
+struct Statement {
+    tokens: Vec<Token>,
+    next: usize,
+    line: usize,
+    end_column: usize,
+}
+
+impl Statement {
+    fn error_at(&self, column: usize, message: String) -> Error {
+        Error::Parse {
+            line: self.line,
+            column,
+            message,
+        }
+    }
+
+    // Error pointing at the most recently read token
+    fn error(&self, message: String) -> Error {
+        let column = self
+            .tokens
+            .get(self.next.saturating_sub(1))
+            .map_or(self.end_column, |t| t.column);
+        self.error_at(column, message)
+    }
+
+    // Error pointing at the path that was just read, for a file it names that couldn't be loaded
+    fn load_error(&self, path: &Path, error: Box<dyn std::error::Error>) -> Error {
+        Error::Load {
+            line: self.line,
+            column: self.tokens[self.next - 1].column,
+            error: Box::new(PathError {
+                path: path.display().to_string(),
+                error,
+            }),
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<&str, Error> {
+        match self.tokens.get(self.next) {
+            Some(token) => {
+                self.next += 1;
+                Ok(&token.text)
+            }
+            None => Err(self.error_at(self.end_column, format!("expected {}", what))),
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.next).map(|t| t.text.as_str())
+    }
+
+    fn number(&mut self, what: &str) -> Result<f64, Error> {
+        let word = self.word(what)?;
+        if let Ok(n) = word.parse() {
+            return Ok(n);
+        }
+        let message = format!("expected {}, found \"{}\"", what, word);
+        Err(self.error(message))
+    }
+
+    fn vec3(&mut self, what: &str) -> Result<Vec3, Error> {
+        Ok(Vec3::from_xyz(
+            self.number(what)?,
+            self.number(what)?,
+            self.number(what)?,
+        ))
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        match self.tokens.get(self.next) {
+            Some(token) => {
+                Err(self.error_at(token.column, format!("unexpected \"{}\"", token.text)))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+struct CameraDef {
+    from: Vec3,
+    at: Vec3,
+    up: Vec3,
+    vfov: f64,
+    aperture: f64,
+    // Distance from the camera to the point it's looking at when not set
+    focus: Option<f64>,
+    time: (f64, f64),
+}
+
+impl Default for CameraDef {
+    fn default() -> Self {
+        Self {
+            from: Vec3::new(),
+            at: Vec3::from_xyz(0.0, 0.0, -1.0),
+            up: Vec3::from_xyz(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aperture: 0.0,
+            focus: None,
+            time: (0.0, 1.0),
+        }
+    }
+}
+
+impl CameraDef {
+    fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.from,
+            self.at,
+            self.up,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus.unwrap_or_else(|| (self.from - self.at).length()),
+            self.time.0,
+            self.time.1,
+        )
+    }
+}
+
+// Options can come in any order
+fn daylight(s: &mut Statement) -> Result<Daylight, Error> {
+    let sun = s.vec3("sun direction")?;
+    if sun.y < 0.0 {
+        return Err(s.error(String::from("sun can't be below the horizon")));
+    }
+    let (mut turbidity, mut albedo, mut intensity) = (3.0, Vec3::from_xyz(0.3, 0.3, 0.3), 1.0);
+    while s.peek().is_some() {
+        match s.word("daylight option")? {
+            "turbidity" => {
+                turbidity = s.number("turbidity")?;
+                if !(2.0..=10.0).contains(&turbidity) {
+                    return Err(s.error(String::from("turbidity must be from 2 to 10")));
+                }
+            }
+            "albedo" => albedo = s.vec3("ground albedo")?,
+            "intensity" => {
+                intensity = s.number("intensity")?;
+                if intensity < 0.0 {
+                    return Err(s.error(String::from("intensity can't be negative")));
+                }
+            }
+            other => {
+                let message = format!("unknown daylight option \"{}\"", other);
+                return Err(s.error(message));
+            }
+        }
+    }
+    Daylight::new(sun, turbidity, albedo, intensity)
+        .ok_or_else(|| s.error(String::from("sun direction can't be 0 0 0")))
+}
+
+// Textures are boxed per material, so keep how to build them instead of the textures themselves
+enum TextureDef {
+    Solid(Vec3),
+    Checker(Arc<TextureDef>, Arc<TextureDef>),
+    // generated once when declared so every material using it gets the same noise
+    Noise(Arc<Perlin>, f64, NoiseStyle),
+    // loaded and decoded to linear once when declared
+    Image(Arc<Image>, Filter, Addressing),
+    VertexColor,
+}
+
+impl TextureDef {
+    fn build(&self) -> Box<dyn TextureWritable> {
+        match self {
+            Self::Solid(color) => Box::new(SolidColor { color: *color }),
+            Self::Checker(even, odd) => Box::new(CheckerTexture {
+                even: even.build(),
+                odd: odd.build(),
+            }),
+            Self::Noise(noise, scale, style) => Box::new(NoiseTexture {
+                noise: Arc::clone(noise),
+                scale: *scale,
+                style: *style,
+            }),
+            Self::Image(image, filter, addressing) => Box::new(ImageTexture {
+                image: Arc::clone(image),
+                filter: *filter,
+                addressing: *addressing,
+            }),
+            Self::VertexColor => Box::new(VertexColorTexture),
+        }
+    }
+}
+
+struct Builder<'a> {
+    dir: &'a Path,
+    // seeded from the scene seed, for noise textures and then the BVH
+    rng: Pcg64Mcg,
+    // for the scene's BVH and each mesh's
+    split: Split,
+    camera: CameraDef,
+    background: Box<dyn EnvironmentWritable>,
+    textures: HashMap<String, Arc<TextureDef>>,
+    materials: HashMap<String, Arc<dyn MaterialWritable>>,
+    // Placement for objects, None while they're used as they are
+    transform: Option<Matrix4>,
+    // Each mesh file loaded so far, by its path and material name, in a BVH of its own
+    meshes: HashMap<(PathBuf, String), Arc<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    // Objects made of emitting materials, which are in objects too
+    lights: Vec<Arc<dyn Hittable>>,
+}
+
+impl<'a> Builder<'a> {
+    fn statement(&mut self, s: &mut Statement) -> Result<(), Error> {
+        let keyword = s.word("statement")?.to_owned();
+        match keyword.as_str() {
+            "camera" => self.camera(s),
+            "background" => {
+                self.background = match s.word("sky, color, map, or daylight")? {
+                    "sky" => Box::new(Gradient),
+                    "color" => Box::new(Solid(s.vec3("background color")?)),
+                    "map" => Box::new(self.environment_map(s)?),
+                    "daylight" => Box::new(daylight(s)?),
+                    other => {
+                        let message = format!("unknown background \"{}\"", other);
+                        return Err(s.error(message));
+                    }
+                };
+                Ok(())
+            }
+            "texture" => {
+                let name = s.word("texture name")?.to_owned();
+                let texture = self.texture(s)?;
+                self.textures.insert(name, Arc::new(texture));
+                Ok(())
+            }
+            "material" => {
+                let name = s.word("material name")?.to_owned();
+                let material = self.material(s)?;
+                // meshes loaded with the name's old material mustn't be placed again under the new one
+                self.meshes
+                    .retain(|(_, material_name), _| *material_name != name);
+                self.materials.insert(name, material);
+                Ok(())
+            }
+            "transform" => self.transform(s),
+            _ => self.object(&keyword, s),
+        }
+    }
+
+    fn camera(&mut self, s: &mut Statement) -> Result<(), Error> {
+        while let Some(key) = s.peek() {
+            let key = key.to_owned();
+            s.next += 1;
+            match key.as_str() {
+                "from" => self.camera.from = s.vec3("camera position")?,
+                "at" => self.camera.at = s.vec3("camera target")?,
+                "up" => self.camera.up = s.vec3("camera up direction")?,
+                "vfov" => self.camera.vfov = s.number("vertical field of view")?,
+                "aperture" => self.camera.aperture = s.number("aperture")?,
+                "focus" => self.camera.focus = Some(s.number("focus distance")?),
+                "time" => {
+                    self.camera.time = (
+                        s.number("shutter open time")?,
+                        s.number("shutter close time")?,
+                    )
+                }
+                _ => return Err(s.error(format!("unknown camera setting \"{}\"", key))),
+            }
+        }
+        Ok(())
+    }
+
+    fn transform(&mut self, s: &mut Statement) -> Result<(), Error> {
+        let mut transform = Matrix4::identity();
+        while let Some(step) = s.peek() {
+            let step = step.to_owned();
+            s.next += 1;
+            let matrix = match step.as_str() {
+                "translate" => Matrix4::translation(s.vec3("offset")?),
+                "rotate" => {
+                    let axis = s.vec3("rotation axis")?;
+                    if axis.length_squared() == 0.0 {
+                        return Err(s.error(String::from("rotation axis can't be 0 0 0")));
+                    }
+                    Matrix4::rotation(axis, s.number("degrees")?)
+                }
+                "scale" => {
+                    let x = s.number("scale")?;
+                    // one factor for all three axes, unless two more follow
+                    if let Some(Ok(_)) = s.peek().map(str::parse::<f64>) {
+                        Matrix4::scaling(Vec3::from_xyz(x, s.number("scale")?, s.number("scale")?))
+                    } else {
+                        Matrix4::scaling(Vec3::from_xyz(x, x, x))
+                    }
+                }
+                "matrix" => {
+                    let mut m = Matrix4::identity();
+                    for row in &mut m.m {
+                        for value in row.iter_mut() {
+                            *value = s.number("matrix value")?;
+                        }
+                    }
+                    m
+                }
+                _ => return Err(s.error(format!("unknown transform step \"{}\"", step))),
+            };
+            // later steps apply after the earlier ones
+            transform = matrix * transform;
+        }
+
+        if transform.inverse().is_none() {
+            let message = String::from("transform flattens objects, it can't be undone");
+            return Err(s.error_at(s.tokens[0].column, message));
+        }
+        self.transform = if transform == Matrix4::identity() {
+            None
+        } else {
+            Some(transform)
+        };
+        Ok(())
+    }
+
+    // The object placed by the current transform
+    fn place(&self, object: Arc<dyn Hittable>) -> Arc<dyn Hittable> {
+        match self.transform {
+            // the transform was checked to be invertible when it was set
+            Some(m) => Arc::new(Transform::new(object, m).unwrap()),
+            None => object,
+        }
+    }
+
+    fn texture(&mut self, s: &mut Statement) -> Result<TextureDef, Error> {
+        match s.word("texture type")? {
+            "solid" => Ok(TextureDef::Solid(s.vec3("color")?)),
+            "checker" => Ok(TextureDef::Checker(
+                self.texture_ref(s)?,
+                self.texture_ref(s)?,
+            )),
+            "noise" => {
+                let scale = s.number("noise scale")?;
+                let style = if s.peek().is_some() {
+                    match s.word("noise style")? {
+                        "smooth" => NoiseStyle::Smooth,
+                        "turbulence" => NoiseStyle::Turbulence,
+                        "marble" => NoiseStyle::Marble,
+                        other => {
+                            let message = format!("unknown noise style \"{}\"", other);
+                            return Err(s.error(message));
+                        }
+                    }
+                } else {
+                    NoiseStyle::Smooth
+                };
+                Ok(TextureDef::Noise(
+                    Arc::new(Perlin::new(&mut self.rng)),
+                    scale,
+                    style,
+                ))
+            }
+            "image" => self.image_texture(s),
+            "vertex_color" => Ok(TextureDef::VertexColor),
+            other => {
+                let message = format!("unknown texture type \"{}\"", other);
+                Err(s.error(message))
+            }
+        }
+    }
+
+    // Options can come in any order, bilinear filtering, wrapping, and sRGB decoding when left out
+    fn image_texture(&self, s: &mut Statement) -> Result<TextureDef, Error> {
+        let path = self.dir.join(s.word("image path")?);
+        let image = image::load(&path).map_err(|e| s.load_error(&path, Box::new(e)))?;
+
+        let (mut filter, mut addressing, mut srgb) = (Filter::Bilinear, Addressing::Wrap, true);
+        while s.peek().is_some() {
+            match s.word("image option")? {
+                "nearest" => filter = Filter::Nearest,
+                "bilinear" => filter = Filter::Bilinear,
+                "wrap" => addressing = Addressing::Wrap,
+                "clamp" => addressing = Addressing::Clamp,
+                "mirror" => addressing = Addressing::Mirror,
+                "srgb" => srgb = true,
+                "linear" => srgb = false,
+                other => {
+                    let message = format!("unknown image option \"{}\"", other);
+                    return Err(s.error(message));
+                }
+            }
+        }
+
+        let image = if srgb { image.decode_srgb() } else { image };
+        Ok(TextureDef::Image(Arc::new(image), filter, addressing))
+    }
+
+    // Options can come in any order, unturned, at its own brightness, and linear (as HDR images are) when left out
+    fn environment_map(&self, s: &mut Statement) -> Result<EnvironmentMap, Error> {
+        let path = self.dir.join(s.word("environment map path")?);
+        let image = image::load(&path).map_err(|e| s.load_error(&path, Box::new(e)))?;
+
+        let (mut rotation, mut intensity, mut srgb) = (0.0, 1.0, false);
+        while s.peek().is_some() {
+            match s.word("environment map option")? {
+                "rotate" => rotation = s.number("degrees")?,
+                "intensity" => {
+                    intensity = s.number("intensity")?;
+                    if intensity < 0.0 {
+                        return Err(s.error(String::from("intensity can't be negative")));
+                    }
+                }
+                "srgb" => srgb = true,
+                "linear" => srgb = false,
+                other => {
+                    let message = format!("unknown environment map option \"{}\"", other);
+                    return Err(s.error(message));
+                }
+            }
+        }
+
+        let image = if srgb { image.decode_srgb() } else { image };
+        Ok(EnvironmentMap::new(image, rotation, intensity))
+    }
+
+    // A declared texture's name, or an inline solid color
+    fn texture_ref(&self, s: &mut Statement) -> Result<Arc<TextureDef>, Error> {
+        if let Some(Ok(_)) = s.peek().map(str::parse::<f64>) {
+            return Ok(Arc::new(TextureDef::Solid(s.vec3("color")?)));
+        }
+        let name = s.word("texture")?;
+        if let Some(t) = self.textures.get(name) {
+            return Ok(Arc::clone(t));
+        }
+        let message = format!("unknown texture \"{}\"", name);
+        Err(s.error(message))
+    }
+
+    fn material(&self, s: &mut Statement) -> Result<Arc<dyn MaterialWritable>, Error> {
+        match s.word("material type")? {
+            "lambertian" => Ok(Arc::new(Lambertian::new(self.texture_ref(s)?.build()))),
+            "metal" => Ok(Arc::new(Metal::new(s.vec3("albedo")?, s.number("fuzz")?))),
+            "dielectric" => Ok(Arc::new(Dielectric::new(s.number("refraction index")?))),
+            "diffuse_light" => Ok(Arc::new(DiffuseLight::new(self.texture_ref(s)?.build()))),
+            other => {
+                let message = format!("unknown material type \"{}\"", other);
+                Err(s.error(message))
+            }
+        }
+    }
+
+    fn material_ref(&self, s: &mut Statement) -> Result<Arc<dyn MaterialWritable>, Error> {
+        let name = s.word("material")?;
+        if let Some(m) = self.materials.get(name) {
+            return Ok(Arc::clone(m));
+        }
+        let message = format!("unknown material \"{}\"", name);
+        Err(s.error(message))
+    }
+
+    fn object(&mut self, keyword: &str, s: &mut Statement) -> Result<(), Error> {
+        match keyword {
+            "obj" | "ply" => {
+                let material_name = s.peek().unwrap_or_default().to_owned();
+                let material = self.material_ref(s)?;
+                let path = self.dir.join(s.word("path")?);
+                let key = (path.clone(), material_name);
+                let mesh = if let Some(mesh) = self.meshes.get(&key) {
+                    Arc::clone(mesh)
+                } else {
+                    let loaded = if keyword == "obj" {
+                        obj::load(&path, &material)
+                            .map(obj::Obj::into_mesh)
+                            .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
+                    } else {
+                        ply::load(&path, &material)
+                            .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
+                    };
+                    let triangles = match loaded {
+                        // a BVH needs something in it, and vertices on their own can't be seen anyway
+                        Ok(mesh) if mesh.triangles.is_empty() => {
+                            return Err(s.load_error(&path, "no faces".into()))
+                        }
+                        Ok(mesh) => mesh.into_hittables(),
+                        Err(error) => return Err(s.load_error(&path, error)),
+                    };
+                    let (time0, time1) = self.camera.time;
+                    let mesh: Arc<dyn Hittable> = Arc::new(FlatBVH::new(
+                        &mut self.rng,
+                        triangles,
+                        time0,
+                        time1,
+                        self.split,
+                    ));
+                    self.meshes.insert(key, Arc::clone(&mesh));
+                    mesh
+                };
+                self.objects.push(self.place(mesh));
+            }
+            "medium" => {
+                // media hits have no surface to shade, so they always scatter isotropically
+                let material: Arc<dyn MaterialWritable> =
+                    Arc::new(Isotropic::new(self.texture_ref(s)?.build()));
+                let density = s.number("density")?;
+                if density <= 0.0 {
+                    return Err(s.error(String::from("density must be more than 0")));
+                }
+                let shape = s.word("boundary shape")?.to_owned();
+                // the boundary's own material is never seen, the medium's is used for every hit inside it
+                if let Some(boundary) = Self::shape(&shape, Arc::clone(&material), s)? {
+                    let medium = Arc::new(ConstantMedium::new(boundary, density, material));
+                    self.objects.push(self.place(medium));
+                } else {
+                    let message = format!("unknown boundary shape \"{}\"", shape);
+                    return Err(s.error(message));
+                }
+            }
+            _ => {
+                if !SHAPES.contains(&keyword) {
+                    let message = format!("unknown statement \"{}\"", keyword);
+                    return Err(s.error(message));
+                }
+                let material = self.material_ref(s)?;
+                let is_light = material.is_light();
+                if let Some(object) = Self::shape(keyword, material, s)? {
+                    let object = self.place(object);
+                    // lights that can't be sampled would only waste the shadow rays sent towards them
+                    if is_light && object.is_sampled() {
+                        self.lights.push(Arc::clone(&object));
+                    }
+                    self.objects.push(object);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // A single shape from the arguments after its material, or None if keyword isn't one of SHAPES
+    fn shape(
+        keyword: &str,
+        material: Arc<dyn MaterialWritable>,
+        s: &mut Statement,
+    ) -> Result<Option<Arc<dyn Hittable>>, Error> {
+        let shape: Arc<dyn Hittable> = match keyword {
+            "sphere" => Arc::new(Sphere {
+                material,
+                center: s.vec3("center")?,
+                radius: s.number("radius")?,
+            }),
+            "moving_sphere" => Arc::new(MovingSphere {
+                material,
+                center0: s.vec3("start center")?,
+                center1: s.vec3("end center")?,
+                time0: s.number("start time")?,
+                time1: s.number("end time")?,
+                radius: s.number("radius")?,
+            }),
+            "xy_rect" => Arc::new(XYRect {
+                material,
+                x0: s.number("x0")?,
+                x1: s.number("x1")?,
+                y0: s.number("y0")?,
+                y1: s.number("y1")?,
+                k: s.number("z")?,
+            }),
+            "xz_rect" => Arc::new(XZRect {
+                material,
+                x0: s.number("x0")?,
+                x1: s.number("x1")?,
+                z0: s.number("z0")?,
+                z1: s.number("z1")?,
+                k: s.number("y")?,
+            }),
+            "yz_rect" => Arc::new(YZRect {
+                material,
+                y0: s.number("y0")?,
+                y1: s.number("y1")?,
+                z0: s.number("z0")?,
+                z1: s.number("z1")?,
+                k: s.number("x")?,
+            }),
+            "box" => Arc::new(Cuboid::new(
+                s.vec3("min corner")?,
+                s.vec3("max corner")?,
+                material,
+            )),
+            "triangle" => Arc::new(Triangle::new(
+                [s.vec3("vertex")?, s.vec3("vertex")?, s.vec3("vertex")?],
+                material,
+            )),
+            "plane" => {
+                let point = s.vec3("point")?;
+                let normal = s.vec3("normal")?;
+                match Plane::new(point, normal, material) {
+                    Some(plane) => Arc::new(plane),
+                    None => return Err(s.error(String::from("normal can't be 0 0 0"))),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(shape))
+    }
+}
+
+// Names the file that failed to load along with why
+#[derive(Debug)]
+struct PathError {
+    path: String,
+    error: Box<dyn std::error::Error>,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+#[cfg(test)]
+mod test {
+    use super::{fs, parse, Error, Path, Split, Vec3};
+    use crate::hdr;
+    use crate::hit::Hittable;
+    use crate::ray::Ray;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn scene() {
+        let mut rng = Pcg64Mcg::new(0);
+        let scene = parse(
+            r#"# a lit checkered ground
+camera from 0 1 5 at 0 1 0 vfov 30
+background color 0 0 0
+
+texture white solid 0.9 0.9 0.9
+texture ground checker white 0.2 0.3 0.1
+material ground lambertian ground
+material light diffuse_light 4 4 4   # inline color
+texture veins noise 4 marble
+material stone lambertian veins
+
+plane ground 0 0 0 0 1 0
+xz_rect light -1 1 -1 1 3
+sphere stone 10 0 0 1
+medium 0.9 0.9 0.9 50 box -20 -1 -1 -18 1 1
+"#,
+            Path::new(""),
+            1.0,
+            0,
+            Split::SurfaceArea,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene.background.color(Vec3::from_xyz(0.0, 1.0, 0.0)),
+            Vec3::new()
+        );
+        let down = Ray {
+            origin: Vec3::from_xyz(0.0, 2.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = scene
+            .world
+            .hit(&down, 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-9);
+        let up = Ray {
+            origin: Vec3::from_xyz(0.0, 2.0, 0.0),
+            direction: Vec3::from_xyz(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = scene
+            .world
+            .hit(&up, 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert_eq!(
+            hit.material.emitted(hit.u, hit.v, hit.point),
+            Vec3::from_xyz(4.0, 4.0, 4.0)
+        );
+        // straight into the dense enough smoke from inside it
+        let left = Ray {
+            origin: Vec3::from_xyz(-19.0, 0.0, 0.0),
+            direction: Vec3::from_xyz(-1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hit = scene
+            .world
+            .hit(&left, 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!(format!("{:?}", hit.material).starts_with("Isotropic"));
+    }
+
+    #[test]
+    fn errors() {
+        let position = |s: &str| match parse(s, Path::new(""), 1.0, 0, Split::SurfaceArea) {
+            Err(Error::Parse { line, column, .. }) | Err(Error::Load { line, column, .. }) => {
+                (line, column)
+            }
+            _ => (0, 0),
+        };
+        assert_eq!(
+            position("material m dielectric 1.5\nsphere m 0 0 zero 1\n"),
+            (2, 14)
+        );
+        assert_eq!(position("sphere glass 0 0 0 1\n"), (1, 8));
+        assert_eq!(
+            position("material m dielectric 1.5\n\nsphere m 0 0 0\n"),
+            (3, 15)
+        );
+        assert_eq!(position("material m dielectric 1.5 extra\n"), (1, 27));
+        assert_eq!(position("camera from 0 0 0 fov 20\n"), (1, 19));
+        assert_eq!(position("  cone m 0 0 0 1\n"), (1, 3));
+        assert_eq!(
+            position("material m dielectric 1.5\nplane m 0 0 0 0 0 0\n"),
+            (2, 19)
+        );
+        assert_eq!(position("texture t noise 4 wavy\n"), (1, 19));
+        assert_eq!(position("material m lambertian \"unterminated\n"), (1, 23));
+        assert_eq!(
+            position("material m dielectric 1.5\nobj m \"missing file.obj\"\n"),
+            (2, 7)
+        );
+        assert_eq!(position("texture t image \"missing.ppm\"\n"), (1, 17));
+        assert_eq!(position("transform scale 1 0 1\n"), (1, 1));
+        assert_eq!(position("transform translate 1 2 3 spin 4\n"), (1, 27));
+        assert_eq!(position("transform rotate 0 0 0 90\n"), (1, 22));
+        let smoke = "texture s solid 1 1 1\n";
+        assert_eq!(
+            position(&format!("{}medium s 0 sphere 0 0 0 1\n", smoke)),
+            (2, 10)
+        );
+        assert_eq!(
+            position(&format!("{}medium s 1 cone 0 0\n", smoke)),
+            (2, 12)
+        );
+        assert_eq!(
+            position(&format!("{}medium s 1 sphere 0 0 0\n", smoke)),
+            (2, 24)
+        );
+        // a material can't stand in for the phase function
+        assert_eq!(
+            position("material s lambertian 1 1 1\nmedium s 1 sphere 0 0 0 1\n"),
+            (2, 8)
+        );
+        assert_eq!(position("# nothing here\n"), (1, 1));
+    }
+
+    #[test]
+    fn transforms() {
+        let dir = std::env::temp_dir().join("raytracer-scene-transforms");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("quad.obj"),
+            "v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\nf 1 2 3 4\n",
+        )
+        .unwrap();
+
+        let scene = parse(
+            "material m lambertian 0.5 0.5 0.5
+transform scale 2 1 1 translate 0 0 -10
+obj m quad.obj
+transform rotate 0 1 0 90 translate 20 0 0
+obj m quad.obj
+transform
+sphere m 0 10 0 1
+",
+            &dir,
+            1.0,
+            0,
+            Split::SurfaceArea,
+        )
+        .unwrap();
+
+        let hit_t = |origin: Vec3, direction: Vec3| {
+            let r = Ray {
+                origin,
+                direction,
+                time: 0.0,
+            };
+            // rounded off, the rotation isn't exact
+            scene
+                .world
+                .hit(&r, 0.001, f64::INFINITY, &mut Pcg64Mcg::new(0))
+                .map(|hit| (hit.t * 1e9).round() / 1e9)
+        };
+        let forward = Vec3::from_xyz(0.0, 0.0, -1.0);
+        // stretched to 4 wide
+        assert_eq!(hit_t(Vec3::from_xyz(1.5, 0.0, 0.0), forward), Some(10.0));
+        assert_eq!(hit_t(Vec3::from_xyz(2.5, 0.0, 0.0), forward), None);
+        // the same mesh turned to face along x
+        let left = Vec3::from_xyz(-1.0, 0.0, 0.0);
+        assert_eq!(hit_t(Vec3::from_xyz(25.0, 0.0, 0.5), left), Some(5.0));
+        // and the sphere after the reset isn't moved
+        let down = Vec3::from_xyz(0.0, -1.0, 0.0);
+        assert_eq!(hit_t(Vec3::from_xyz(0.0, 20.0, 0.0), down), Some(9.0));
+    }
+
+    #[test]
+    fn image_texture() {
+        let dir = std::env::temp_dir().join("raytracer-scene-image");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("gray.ppm"), "P3 1 1 255 188 188 188\n").unwrap();
+        fs::write(dir.join("broken.ppm"), "P3 1 1 255 188\n").unwrap();
+
+        let color = |options: &str| {
+            let scene = parse(
+                &format!(
+                    "texture t image gray.ppm {}\nmaterial m diffuse_light t\nsphere m 0 0 -2 1\n",
+                    options
+                ),
+                &dir,
+                1.0,
+                0,
+                Split::SurfaceArea,
+            )
+            .unwrap();
+            let forward = Ray {
+                origin: Vec3::new(),
+                direction: Vec3::from_xyz(0.0, 0.0, -1.0),
+                time: 0.0,
+            };
+            let hit = scene
+                .world
+                .hit(&forward, 0.001, f64::INFINITY, &mut Pcg64Mcg::new(0))
+                .unwrap();
+            hit.material.emitted(hit.u, hit.v, hit.point).r()
+        };
+        // sRGB decoded by default
+        assert!((color("") - 0.502_886).abs() < 1e-6);
+        assert!((color("nearest clamp linear") - 188.0 / 255.0).abs() < 1e-12);
+
+        let error = parse(
+            "texture t image broken.ppm\n",
+            &dir,
+            1.0,
+            0,
+            Split::SurfaceArea,
+        );
+        assert!(matches!(
+            error,
+            Err(Error::Load {
+                line: 1,
+                column: 17,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(
+                "texture t image gray.ppm blurry\n",
+                &dir,
+                1.0,
+                0,
+                Split::SurfaceArea
+            ),
+            Err(Error::Parse { column: 26, .. })
+        ));
+    }
+
+    #[test]
+    fn lights() {
+        let has_lights = |objects: &str| {
+            let source = format!("material l diffuse_light 4 4 4\n{}", objects);
+            parse(&source, Path::new(""), 1.0, 0, Split::SurfaceArea)
+                .unwrap()
+                .world
+                .has_lights()
+        };
+        assert!(has_lights("sphere l 0 0 0 1\n"));
+        assert!(has_lights("xz_rect l -1 1 -1 1 3\n"));
+        // still seen by rays that hit them, but there's no way to pick a direction towards them
+        assert!(!has_lights(
+            "box l -1 -1 -1 1 1 1\ntriangle l 0 0 0 1 0 0 0 1 0\nplane l 0 0 0 0 1 0\nmoving_sphere l 0 0 0 0 1 0 0 1 1\n"
+        ));
+        assert!(!has_lights("transform translate 0 1 0\nsphere l 0 0 0 1\n"));
+    }
+
+    #[test]
+    fn vertex_colors() {
+        let dir = std::env::temp_dir().join("raytracer-scene-vertex-colors");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("triangle.ply"),
+            "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+-1 -1 0 255 0 0
+2 -1 0 0 255 0
+-1 2 0 0 0 255
+3 0 1 2
+",
+        )
+        .unwrap();
+
+        let scene = parse(
+            "texture colors vertex_color
+material m lambertian colors
+transform translate 0 0 -10
+ply m triangle.ply
+",
+            &dir,
+            1.0,
+            0,
+            Split::SurfaceArea,
+        )
+        .unwrap();
+
+        let r = Ray {
+            origin: Vec3::new(),
+            direction: Vec3::from_xyz(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = scene
+            .world
+            .hit(&r, 0.001, f64::INFINITY, &mut Pcg64Mcg::new(0))
+            .unwrap();
+        // the middle of the triangle is a third of each corner's color
+        let color = hit.color.unwrap();
+        assert!((color - Vec3::from_xyz(1.0, 1.0, 1.0) / 3.0).length() < 1e-9);
+        assert!(format!("{:?}", hit.material).contains("VertexColorTexture"));
+    }
+
+    #[test]
+    fn redefined_material() {
+        let dir = std::env::temp_dir().join("raytracer-scene-redefined-material");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("quad.obj"),
+            "v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\nf 1 2 3 4\n",
+        )
+        .unwrap();
+
+        let scene = parse(
+            "material m lambertian 0.5 0.5 0.5
+transform translate 0 0 -10
+obj m quad.obj
+material m metal 0.9 0.9 0.9 0
+transform translate 10 0 -10
+obj m quad.obj
+",
+            &dir,
+            1.0,
+            0,
+            Split::SurfaceArea,
+        )
+        .unwrap();
+
+        let material_at = |x: f64| {
+            let r = Ray {
+                origin: Vec3::from_xyz(x, 0.0, 0.0),
+                direction: Vec3::from_xyz(0.0, 0.0, -1.0),
+                time: 0.0,
+            };
+            let hit = scene
+                .world
+                .hit(&r, 0.001, f64::INFINITY, &mut Pcg64Mcg::new(0))
+                .unwrap();
+            format!("{:?}", hit.material)
+        };
+        assert!(material_at(0.0).starts_with("Lambertian"));
+        assert!(material_at(10.0).starts_with("Metal"));
+    }
+
+    #[test]
+    fn empty_meshes() {
+        let dir = std::env::temp_dir().join("raytracer-scene-empty-meshes");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("points.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\n").unwrap();
+        fs::write(
+            dir.join("points.ply"),
+            "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n",
+        )
+        .unwrap();
+
+        for file in &["points.obj", "points.ply"] {
+            let keyword = &file[file.len() - 3..];
+            let source = format!(
+                "material m lambertian 0.5 0.5 0.5\n{} m {}\n",
+                keyword, file
+            );
+            match parse(&source, &dir, 1.0, 0, Split::SurfaceArea) {
+                Err(Error::Load {
+                    line: 2,
+                    column: 7,
+                    error,
+                }) => assert!(error.to_string().ends_with("no faces")),
+                _ => panic!("expected a load error for {}", file),
+            }
+        }
+    }
+
+    #[test]
+    fn environment_map() {
+        let dir = std::env::temp_dir().join("raytracer-scene-environment");
+        fs::create_dir_all(&dir).unwrap();
+        // bright on the left half, seen from the middle of the map, and dim on the right
+        let colors: Vec<Vec3> = (0..16)
+            .map(|i| Vec3::from_xyz(if i % 4 < 2 { 8.0 } else { 0.5 }, 0.0, 0.0))
+            .collect();
+        fs::write(dir.join("sky.hdr"), hdr::image(4, 4, &colors, 1)).unwrap();
+
+        let color = |options: &str, direction: Vec3| {
+            let scene = parse(
+                &format!(
+                    "background map sky.hdr {}\nmaterial m lambertian 1 1 1\nsphere m 0 0 0 1\n",
+                    options
+                ),
+                &dir,
+                1.0,
+                0,
+                Split::SurfaceArea,
+            )
+            .unwrap();
+            assert!(scene.background.is_sampled());
+            scene.background.color(direction).r()
+        };
+        let left = Vec3::from_xyz(-1.0, 0.0, -0.1);
+        assert!((color("", left) - 8.0).abs() < f64::EPSILON);
+        assert!((color("intensity 0.5", left) - 4.0).abs() < f64::EPSILON);
+        assert!((color("rotate 180 intensity 2", left) - 1.0).abs() < f64::EPSILON);
+
+        let position = |source: &str| match parse(source, &dir, 1.0, 0, Split::SurfaceArea) {
+            Err(Error::Parse { line, column, .. }) | Err(Error::Load { line, column, .. }) => {
+                (line, column)
+            }
+            _ => (0, 0),
+        };
+        assert_eq!(position("background map sky.hdr intensity -1\n"), (1, 34));
+        assert_eq!(position("background map sky.hdr spin 90\n"), (1, 24));
+        assert_eq!(position("background map missing.hdr\n"), (1, 16));
+        assert_eq!(position("background gradient\n"), (1, 12));
+    }
+
+    #[test]
+    fn daylight() {
+        let scene = parse(
+            "background daylight 0 1 -1 turbidity 4 albedo 0.2 0.2 0.2\nmaterial m lambertian 1 1 1\nsphere m 0 0 0 1\n",
+            Path::new(""),
+            1.0,
+            0,
+            Split::SurfaceArea,
+        )
+        .unwrap();
+        assert!(scene.background.is_sampled());
+        let sun = scene.background.color(Vec3::from_xyz(0.0, 1.0, -1.0));
+        let sky = scene.background.color(Vec3::from_xyz(0.0, 1.0, 1.0));
+        assert!(sun.g() > 1000.0 * sky.g());
+
+        let position = |source: &str| match parse(source, Path::new(""), 1.0, 0, Split::SurfaceArea)
+        {
+            Err(Error::Parse { line, column, .. }) => (line, column),
+            _ => (0, 0),
+        };
+        assert_eq!(position("background daylight 0 -1 0\n"), (1, 26));
+        assert_eq!(position("background daylight 0 0 0\n"), (1, 25));
+        assert_eq!(position("background daylight 0 1 0 turbidity 1\n"), (1, 37));
+        assert_eq!(position("background daylight 0 1 0 haze 2\n"), (1, 27));
+    }
+}
@@ -1,17 +1,20 @@
 use super::aabb::AABB;
 use super::hit::{Hit, Hittable};
 use super::material::{Lambertian, MaterialWritable};
+use super::onb::ONB;
 use super::ray::Ray;
 use super::texture::SolidColor;
 use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
 use std::f64::consts::PI;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
-    pub material: Rc<dyn MaterialWritable>,
+    pub material: Arc<dyn MaterialWritable>,
 }
 
 impl Sphere {
@@ -19,7 +22,7 @@ impl Sphere {
         Self {
             center: Vec3::new(),
             radius: 0.0,
-            material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                 0.5, 0.5, 0.5,
             )))),
         }
@@ -31,6 +34,16 @@ impl Sphere {
         (1.0 - (phi + PI) / (2.0 * PI), (theta + PI / 2.0) / PI)
     }
 
+    // Cosine of the angle between the center and the sphere's edge seen from origin, None from inside the sphere
+    fn cos_max(&self, origin: Vec3) -> Option<f64> {
+        let distance_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / distance_squared).sqrt())
+    }
+
     fn compute_hit(&self, r: &Ray, t: f64) -> Option<Hit> {
         let point = r.at(t);
         let mut normal = (point - self.center) / self.radius;
@@ -49,7 +62,8 @@ impl Sphere {
             u,
             v,
             front_face,
-            material: Rc::clone(&self.material),
+            color: None,
+            material: Arc::clone(&self.material),
         })
     }
 }
@@ -61,7 +75,7 @@ impl Default for Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
         let oc = r.origin - self.center;
         let a = r.direction.length_squared();
         let half_b = oc.dot(r.direction);
@@ -89,11 +103,38 @@ impl Hittable for Sphere {
             max: self.center + Vec3::from_xyz(self.radius, self.radius, self.radius),
         })
     }
+
+    fn is_sampled(&self) -> bool {
+        true
+    }
+
+    // Evenly over the cone of directions the sphere covers, which can't be done from inside it
+    fn random_direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        let to_center = self.center - origin;
+        let cos_max = self.cos_max(origin)?;
+
+        let z = rng.gen::<f64>().mul_add(cos_max - 1.0, 1.0);
+        let (sin_phi, cos_phi) = (2.0 * PI * rng.gen::<f64>()).sin_cos();
+        let sin_theta = z.mul_add(-z, 1.0).sqrt();
+
+        Some(ONB::from_w(to_center).local(cos_phi * sin_theta, sin_phi * sin_theta, z))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, _time: f64) -> f64 {
+        let to_center = (self.center - origin).unit_vector();
+        match self.cos_max(origin) {
+            // the directions inside the cone are the ones that hit the sphere
+            Some(cos_max) if direction.unit_vector().dot(to_center) >= cos_max => {
+                1.0 / (2.0 * PI * (1.0 - cos_max))
+            }
+            _ => 0.0,
+        }
+    }
 }
 
 impl Hittable for &Sphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
-        (*self).hit(r, t_min, t_max)
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        (*self).hit(r, t_min, t_max, rng)
     }
 
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
@@ -104,14 +145,16 @@ impl Hittable for &Sphere {
 #[cfg(test)]
 #[allow(clippy::unreadable_literal)]
 mod test {
-    use super::{Hit, Hittable, Lambertian, Ray, Rc, SolidColor, Sphere, Vec3};
+    use super::{Arc, Hit, Hittable, Lambertian, Ray, SolidColor, Sphere, Vec3};
+    use rand_pcg::Pcg64Mcg;
 
     #[test]
     fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
         let s = &Sphere {
             center: Vec3::from_xyz(0.0, 0.0, 1.0),
             radius: 0.5,
-            material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+            material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                 0.5, 0.5, 0.5,
             )))),
         };
@@ -131,7 +174,7 @@ mod test {
             time: 0.0,
         };
         assert_eq!(
-            s.hit(&hit_ray, 0.0, 2.0),
+            s.hit(&hit_ray, 0.0, 2.0, &mut rng),
             Some(Hit {
                 point: Vec3 {
                     x: 0.10787389667339245,
@@ -147,13 +190,14 @@ mod test {
                 u: 0.7133877076054168,
                 v: 0.6049005077430056,
                 front_face: true,
-                material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                color: None,
+                material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                     0.5, 0.5, 0.5
                 ))),),
             })
         );
         assert_eq!(
-            s.hit(&inside_hit_ray, 0.0, 1.0),
+            s.hit(&inside_hit_ray, 0.0, 1.0, &mut rng),
             Some(Hit {
                 point: Vec3 {
                     x: 0.0,
@@ -169,12 +213,13 @@ mod test {
                 u: 0.5,
                 v: 1.0,
                 front_face: false,
-                material: Rc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                color: None,
+                material: Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
                     0.5, 0.5, 0.5
                 ))),),
             })
         );
-        assert_eq!(s.hit(&hit_ray, 0.0, 0.5), None);
-        assert_eq!(s.hit(&miss_ray, 0.0, 10.0), None);
+        assert_eq!(s.hit(&hit_ray, 0.0, 0.5, &mut rng), None);
+        assert_eq!(s.hit(&miss_ray, 0.0, 10.0, &mut rng), None);
     }
 }
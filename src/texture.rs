@@ -1,10 +1,20 @@
+use super::hit::Hit;
+use super::image::Image;
+use super::perlin::Perlin;
 use super::vec3::Vec3;
+use rand::Rng;
+use std::sync::Arc;
 
 pub trait Texture {
     fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+
+    // Color at a hit, which is value at its texture coordinates and point unless the texture uses more of it
+    fn value_at(&self, hit: &Hit) -> Vec3 {
+        self.value(hit.u, hit.v, hit.point)
+    }
 }
 
-pub trait TextureWritable: Texture + std::fmt::Debug {}
+pub trait TextureWritable: Texture + std::fmt::Debug + Send + Sync {}
 
 #[derive(Debug)]
 pub struct SolidColor {
@@ -42,6 +52,278 @@ impl Texture for CheckerTexture {
             self.even.value(u, v, p)
         }
     }
+
+    fn value_at(&self, hit: &Hit) -> Vec3 {
+        let p = hit.point;
+        let sines = (10.0 * p.x).sin() * (10.0 * p.y).sin() * (10.0 * p.z).sin();
+        if sines < 0.0 {
+            self.odd.value_at(hit)
+        } else {
+            self.even.value_at(hit)
+        }
+    }
 }
 
 impl TextureWritable for CheckerTexture {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseStyle {
+    // Soft blobs of the noise itself
+    Smooth,
+    // Several octaves of noise, like a rough stone surface
+    Turbulence,
+    // Stripes along z whose phase is pushed around by turbulence, like veins in marble
+    Marble,
+}
+
+// Grayscale Perlin noise in space, scale is the noise's frequency
+#[derive(Debug)]
+pub struct NoiseTexture {
+    pub noise: Arc<Perlin>,
+    pub scale: f64,
+    pub style: NoiseStyle,
+}
+
+impl NoiseTexture {
+    // Octaves of turbulence summed for the turbulence and marble styles
+    const TURBULENCE_DEPTH: u32 = 7;
+
+    pub fn new<T: Rng>(rng: &mut T, scale: f64, style: NoiseStyle) -> Self {
+        Self {
+            noise: Arc::new(Perlin::new(rng)),
+            scale,
+            style,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let scaled = p * self.scale;
+        let shade = match self.style {
+            // noise runs from -1 to 1
+            NoiseStyle::Smooth => 0.5 * (1.0 + self.noise.noise(scaled)),
+            NoiseStyle::Turbulence => self.noise.turbulence(scaled, Self::TURBULENCE_DEPTH),
+            // the stripes get the scale, the turbulence bending them stays at its own size
+            NoiseStyle::Marble => {
+                let turbulence = self.noise.turbulence(p, Self::TURBULENCE_DEPTH);
+                0.5 * (1.0 + 10.0_f64.mul_add(turbulence, scaled.z).sin())
+            }
+        };
+        Vec3::from_xyz(shade, shade, shade)
+    }
+}
+
+impl TextureWritable for NoiseTexture {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    // The texel the point falls in, blocky up close
+    Nearest,
+    // A blend of the four texels around the point
+    Bilinear,
+}
+
+// What's sampled for coordinates outside 0 to 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Addressing {
+    // The image repeats
+    Wrap,
+    // The edge texels stretch out forever
+    Clamp,
+    // The image repeats, flipped every other time so edges meet seamlessly
+    Mirror,
+}
+
+impl Addressing {
+    // Texel for index i along an axis size texels long, i may be off either end
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_wrap
+    )]
+    fn texel(self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+        let texel = match self {
+            Self::Wrap => i.rem_euclid(size),
+            Self::Clamp => i.max(0).min(size - 1),
+            Self::Mirror => {
+                let m = i.rem_euclid(2 * size);
+                if m < size {
+                    m
+                } else {
+                    2 * size - 1 - m
+                }
+            }
+        };
+        texel as usize
+    }
+}
+
+// An image stretched over u and v from 0 to 1, with v = 0 along the bottom row
+#[derive(Debug)]
+pub struct ImageTexture {
+    pub image: Arc<Image>,
+    pub filter: Filter,
+    pub addressing: Addressing,
+}
+
+impl ImageTexture {
+    #[allow(clippy::cast_possible_truncation)]
+    fn texel(&self, x: f64, y: f64) -> Vec3 {
+        self.image.pixel(
+            self.addressing.texel(x as i64, self.image.width),
+            self.addressing.texel(y as i64, self.image.height),
+        )
+    }
+}
+
+impl Texture for ImageTexture {
+    #[allow(clippy::cast_precision_loss)]
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        // in texels, with rows counted from the top
+        let x = u * self.image.width as f64;
+        let y = (1.0 - v) * self.image.height as f64;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor(), y.floor()),
+            Filter::Bilinear => {
+                // texel centers are half way across them
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let top = self.texel(x0, y0) * (1.0 - tx) + self.texel(x0 + 1.0, y0) * tx;
+                let bottom =
+                    self.texel(x0, y0 + 1.0) * (1.0 - tx) + self.texel(x0 + 1.0, y0 + 1.0) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+        }
+    }
+}
+
+impl TextureWritable for ImageTexture {}
+
+// Colors blended from the corners of the triangle hit, for meshes that have vertex colors. Anything without
+// them is white.
+#[derive(Debug)]
+pub struct VertexColorTexture;
+
+impl Texture for VertexColorTexture {
+    fn value(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+        Vec3::from_xyz(1.0, 1.0, 1.0)
+    }
+
+    fn value_at(&self, hit: &Hit) -> Vec3 {
+        hit.color
+            .unwrap_or_else(|| self.value(hit.u, hit.v, hit.point))
+    }
+}
+
+impl TextureWritable for VertexColorTexture {}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Addressing, Arc, CheckerTexture, Filter, Hit, Image, ImageTexture, NoiseStyle,
+        NoiseTexture, Texture, Vec3, VertexColorTexture,
+    };
+    use crate::material::Dielectric;
+    use rand_pcg::Pcg64Mcg;
+
+    #[test]
+    fn image() {
+        let black = Vec3::new();
+        let white = Vec3::from_xyz(1.0, 1.0, 1.0);
+        // black on the left, white on the right
+        let image = Arc::new(Image {
+            width: 2,
+            height: 1,
+            pixels: vec![black, white],
+        });
+        let texture = |filter, addressing| ImageTexture {
+            image: Arc::clone(&image),
+            filter,
+            addressing,
+        };
+        let p = Vec3::new();
+
+        let nearest = texture(Filter::Nearest, Addressing::Wrap);
+        assert_eq!(nearest.value(0.2, 0.5, p), black);
+        assert_eq!(nearest.value(0.7, 0.5, p), white);
+        assert_eq!(nearest.value(1.2, 0.5, p), black);
+        assert_eq!(nearest.value(-0.2, 0.5, p), white);
+
+        let clamp = texture(Filter::Nearest, Addressing::Clamp);
+        assert_eq!(clamp.value(3.2, 0.5, p), white);
+        assert_eq!(clamp.value(-3.7, 0.5, p), black);
+
+        let mirror = texture(Filter::Nearest, Addressing::Mirror);
+        assert_eq!(mirror.value(1.2, 0.5, p), white);
+        assert_eq!(mirror.value(1.7, 0.5, p), black);
+        assert_eq!(mirror.value(-0.2, 0.5, p), black);
+
+        // half way between the texel centers, and blending across the wrapped edge
+        let bilinear = texture(Filter::Bilinear, Addressing::Wrap);
+        assert_eq!(bilinear.value(0.5, 0.5, p), Vec3::from_xyz(0.5, 0.5, 0.5));
+        assert_eq!(bilinear.value(0.75, 0.5, p), white);
+        assert_eq!(
+            bilinear.value(0.875, 0.5, p),
+            Vec3::from_xyz(0.75, 0.75, 0.75)
+        );
+        let bilinear = texture(Filter::Bilinear, Addressing::Clamp);
+        assert_eq!(bilinear.value(0.875, 0.5, p), white);
+    }
+
+    #[test]
+    fn noise() {
+        let p = Vec3::from_xyz(0.3, 1.7, -2.1);
+        for &style in &[
+            NoiseStyle::Smooth,
+            NoiseStyle::Turbulence,
+            NoiseStyle::Marble,
+        ] {
+            let a = NoiseTexture::new(&mut Pcg64Mcg::new(3), 4.0, style);
+            let b = NoiseTexture::new(&mut Pcg64Mcg::new(3), 4.0, style);
+            let color = a.value(0.0, 0.0, p);
+            assert_eq!(color, b.value(0.0, 0.0, p));
+            assert!(color.x >= 0.0 && color.x <= 1.5);
+            assert!((color.x - color.y).abs() < 1e-12 && (color.x - color.z).abs() < 1e-12);
+        }
+
+        let smooth = NoiseTexture::new(&mut Pcg64Mcg::new(3), 1.0, NoiseStyle::Smooth);
+        // noise is 0 on the lattice, so the smooth style is mid grey there
+        assert_eq!(
+            smooth.value(0.0, 0.0, Vec3::from_xyz(2.0, 3.0, 4.0)),
+            Vec3::from_xyz(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn vertex_color() {
+        let mut hit = Hit {
+            point: Vec3::new(),
+            normal: Vec3::from_xyz(0.0, 0.0, 1.0),
+            t: 1.0,
+            u: 0.5,
+            v: 0.5,
+            front_face: true,
+            color: Some(Vec3::from_xyz(0.5, 0.25, 0.25)),
+            material: Arc::new(Dielectric::new(1.5)),
+        };
+        assert_eq!(
+            VertexColorTexture.value_at(&hit),
+            Vec3::from_xyz(0.5, 0.25, 0.25)
+        );
+        // checkers pass the hit on, so their squares can be vertex colored too
+        let checker = CheckerTexture {
+            odd: Box::new(VertexColorTexture),
+            even: Box::new(VertexColorTexture),
+        };
+        assert_eq!(checker.value_at(&hit), Vec3::from_xyz(0.5, 0.25, 0.25));
+        hit.color = None;
+        assert_eq!(
+            VertexColorTexture.value_at(&hit),
+            Vec3::from_xyz(1.0, 1.0, 1.0)
+        );
+    }
+}
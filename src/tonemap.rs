@@ -0,0 +1,140 @@
+use super::vec3::Vec3;
+
+// Compressing linear radiance into the 0 to 1 range of a display, then encoding it for sRGB
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    // Anything above 1 is cut off
+    Clamp,
+    // c / (1 + c), never quite reaches white
+    Reinhard,
+    // Reinhard stretched so that white maps to 1
+    ExtendedReinhard { white: f64 },
+    // Stephen Hill's fit of the ACES reference rendering and sRGB output transforms
+    Aces,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMap {
+    pub operator: Operator,
+    // Stops to brighten (or darken, when negative) the image by before the operator
+    pub exposure: f64,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self {
+            operator: Operator::Clamp,
+            exposure: 0.0,
+        }
+    }
+}
+
+impl ToneMap {
+    // sRGB encoded display values from 0 to 1 for linear radiance
+    pub fn apply(&self, linear: Vec3) -> Vec3 {
+        let c = linear * 2_f64.powf(self.exposure);
+        let mapped = match self.operator {
+            Operator::Clamp => c,
+            Operator::Reinhard => map_channels(c, |x| x / (1.0 + x)),
+            Operator::ExtendedReinhard { white } => {
+                map_channels(c, |x| x * (1.0 + x / (white * white)) / (1.0 + x))
+            }
+            Operator::Aces => aces(c),
+        };
+        map_channels(mapped, |x| srgb_oetf(x.clamp(0.0, 1.0)))
+    }
+}
+
+fn map_channels<F: Fn(f64) -> f64>(c: Vec3, f: F) -> Vec3 {
+    Vec3::from_xyz(f(c.r()), f(c.g()), f(c.b()))
+}
+
+fn mat_mul(m: &[[f64; 3]; 3], c: Vec3) -> Vec3 {
+    let row = |r: &[f64; 3]| r[0] * c.r() + r[1] * c.g() + r[2] * c.b();
+    Vec3::from_xyz(row(&m[0]), row(&m[1]), row(&m[2]))
+}
+
+fn aces(c: Vec3) -> Vec3 {
+    // sRGB to the ACES working space, with the reference rendering transform's saturation folded in
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    // the output transform's desaturation, then back to sRGB
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let fitted = map_channels(mat_mul(&INPUT, c), |v| {
+        (v * (v + 0.024_578_6) - 0.000_090_537) / (v * (0.983_729 * v + 0.432_951) + 0.238_081)
+    });
+    mat_mul(&OUTPUT, fitted)
+}
+
+// The piecewise sRGB transfer function, linear near black and a 1/2.4 power above
+pub fn srgb_oetf(c: f64) -> f64 {
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055_f64.mul_add(c.powf(1.0 / 2.4), -0.055)
+    }
+}
+
+// Its inverse, linear values for sRGB encoded ones
+pub fn srgb_eotf(c: f64) -> f64 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{srgb_eotf, srgb_oetf, Operator, ToneMap, Vec3};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn srgb() {
+        assert!(close(srgb_oetf(0.0), 0.0));
+        assert!(close(srgb_oetf(0.002), 0.02584));
+        assert!(close(srgb_oetf(0.5), 0.73536));
+        assert!(close(srgb_oetf(1.0), 1.0));
+        for &c in &[0.0, 0.002, 0.04, 0.3, 0.9, 1.0] {
+            assert!((srgb_eotf(srgb_oetf(c)) - c).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn operators() {
+        let map = |operator, exposure, v: f64| {
+            ToneMap { operator, exposure }
+                .apply(Vec3::from_xyz(v, v, v))
+                .r()
+        };
+
+        assert!(close(map(Operator::Clamp, 0.0, 0.5), srgb_oetf(0.5)));
+        assert!(close(map(Operator::Clamp, 0.0, 3.0), 1.0));
+        assert!(close(map(Operator::Clamp, -1.0, 1.0), srgb_oetf(0.5)));
+
+        assert!(close(map(Operator::Reinhard, 0.0, 1.0), srgb_oetf(0.5)));
+        assert!(map(Operator::Reinhard, 0.0, 1000.0) < 1.0);
+        assert!(close(map(Operator::Reinhard, 1.0, 1.5), srgb_oetf(0.75)));
+
+        let extended = Operator::ExtendedReinhard { white: 4.0 };
+        assert!(close(map(extended, 0.0, 4.0), 1.0));
+        assert!(map(extended, 0.0, 1.0) > map(Operator::Reinhard, 0.0, 1.0));
+
+        // the fit is black at 0, darkens middle grey a little, and saturates to white
+        assert!(close(map(Operator::Aces, 0.0, 0.0), 0.0));
+        assert!(close(map(Operator::Aces, 0.0, 0.18), srgb_oetf(0.105_59)));
+        assert!(close(map(Operator::Aces, 0.0, 100.0), 1.0));
+    }
+}
@@ -0,0 +1,157 @@
+use super::aabb::AABB;
+use super::hit::{Hit, Hittable};
+use super::matrix::Matrix4;
+use super::ray::Ray;
+use super::vec3::Vec3;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// An object placed in the world by an affine transform, the same object can be shared by any number of these
+pub struct Transform {
+    pub object: Arc<dyn Hittable>,
+    to_world: Matrix4,
+    to_object: Matrix4,
+    // Normals go back to the world by the inverse transpose, so they stay perpendicular under non-uniform scales
+    normal_to_world: Matrix4,
+}
+
+impl Transform {
+    // None if the matrix can't be inverted
+    pub fn new(object: Arc<dyn Hittable>, to_world: Matrix4) -> Option<Self> {
+        let to_object = to_world.inverse()?;
+        Some(Self {
+            object,
+            to_world,
+            to_object,
+            normal_to_world: to_object.transpose(),
+        })
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        // the direction isn't normalized, so t is the same along both rays
+        let object_ray = Ray {
+            origin: self.to_object.point(r.origin),
+            direction: self.to_object.vector(r.direction),
+            time: r.time,
+        };
+
+        self.object
+            .hit(&object_ray, t_min, t_max, rng)
+            .map(|mut hit| {
+                hit.point = self.to_world.point(hit.point);
+                hit.normal = self.normal_to_world.vector(hit.normal).unit_vector();
+                hit
+            })
+    }
+
+    // Around all eight corners of the object's box after they're transformed, which still holds all of the object
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        let object_box = self.object.bounding_box(t0, t1)?;
+        let corners = (0..8).map(|i| {
+            let pick = |bit: usize, min: f64, max: f64| if i & bit == 0 { min } else { max };
+            self.to_world.point(Vec3::from_xyz(
+                pick(1, object_box.min.x, object_box.max.x),
+                pick(2, object_box.min.y, object_box.max.y),
+                pick(4, object_box.min.z, object_box.max.z),
+            ))
+        });
+
+        corners
+            .map(|corner| AABB {
+                min: corner,
+                max: corner,
+            })
+            .fold(None, |bounds: Option<AABB>, corner| {
+                Some(bounds.map_or(corner, |b| b.surrounding_box(&corner)))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hittable, Matrix4, Ray, Transform, Vec3};
+    use crate::sphere::Sphere;
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    fn unit_sphere() -> Arc<Sphere> {
+        Arc::new(Sphere {
+            center: Vec3::new(),
+            radius: 1.0,
+            ..Sphere::default()
+        })
+    }
+
+    #[test]
+    fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
+        // squashed to half height, then moved up 10
+        let m = Matrix4::translation(Vec3::from_xyz(0.0, 10.0, 0.0))
+            * Matrix4::scaling(Vec3::from_xyz(2.0, 0.5, 2.0));
+        let ellipsoid = Transform::new(unit_sphere(), m).unwrap();
+
+        let down = Ray {
+            origin: Vec3::from_xyz(0.0, 20.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -2.0, 0.0),
+            time: 0.0,
+        };
+        let hit = ellipsoid
+            .hit(&down, 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!((hit.t - 4.75).abs() < 1e-9);
+        assert!((hit.point - Vec3::from_xyz(0.0, 10.5, 0.0)).length() < 1e-9);
+        assert!(hit.front_face);
+
+        // the normal where the surface slopes is perpendicular to the squashed surface, not the sphere's
+        let across = Ray {
+            origin: Vec3::from_xyz(1.0, 20.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = ellipsoid
+            .hit(&across, 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        let local = hit.point - Vec3::from_xyz(0.0, 10.0, 0.0);
+        // gradient of x^2/4 + y^2/0.25 + z^2/4
+        let expected = Vec3::from_xyz(local.x / 4.0, local.y / 0.25, 0.0).unit_vector();
+        assert!((hit.normal - expected).length() < 1e-9);
+        assert!((hit.normal.length() - 1.0).abs() < 1e-12);
+
+        let miss = Ray {
+            origin: Vec3::from_xyz(2.5, 20.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(ellipsoid
+            .hit(&miss, 0.001, f64::INFINITY, &mut rng)
+            .is_none());
+
+        assert!(Transform::new(unit_sphere(), Matrix4::scaling(Vec3::new())).is_none());
+    }
+
+    #[test]
+    fn bounding_box() {
+        // the box is around the object's turned box, so an eighth of a turn about z widens it
+        let turned = Transform::new(
+            unit_sphere(),
+            Matrix4::rotation(Vec3::from_xyz(0.0, 0.0, 1.0), 45.0),
+        )
+        .unwrap();
+        let b = turned.bounding_box(0.0, 1.0).unwrap();
+        let diagonal = 2.0_f64.sqrt();
+        assert!((b.max - Vec3::from_xyz(diagonal, diagonal, 1.0)).length() < 1e-9);
+        assert!((b.min + Vec3::from_xyz(diagonal, diagonal, 1.0)).length() < 1e-9);
+
+        let moved = Transform::new(
+            unit_sphere(),
+            Matrix4::translation(Vec3::from_xyz(5.0, 0.0, 0.0))
+                * Matrix4::scaling(Vec3::from_xyz(1.0, 3.0, 1.0)),
+        )
+        .unwrap();
+        let b = moved.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!(b.min, Vec3::from_xyz(4.0, -3.0, -1.0));
+        assert_eq!(b.max, Vec3::from_xyz(6.0, 3.0, 1.0));
+    }
+}
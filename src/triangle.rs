@@ -0,0 +1,296 @@
+use super::aabb::AABB;
+use super::hit::{Hit, Hittable};
+use super::material::MaterialWritable;
+use super::ray::Ray;
+use super::vec3::Vec3;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// Triangles lying in an axis plane have a flat bounding box, pad every axis so the BVH slab test still hits them
+const PADDING: f64 = 0.0001;
+
+#[derive(Debug)]
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    // Per-vertex shading normals, the face normal is used when missing
+    pub normals: Option<[Vec3; 3]>,
+    // Per-vertex texture coordinates, barycentric coordinates are used when missing
+    pub uvs: Option<[(f64, f64); 3]>,
+    // Per-vertex colors, blended into the hit for textures that use them
+    pub colors: Option<[Vec3; 3]>,
+    pub material: Arc<dyn MaterialWritable>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Vec3; 3], material: Arc<dyn MaterialWritable>) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            colors: None,
+            material,
+        }
+    }
+
+    pub fn face_normal(&self) -> Vec3 {
+        (self.vertices[1] - self.vertices[0]).cross(self.vertices[2] - self.vertices[0])
+    }
+
+    fn compute_hit(&self, r: &Ray, t: f64, barycentric: [f64; 3]) -> Option<Hit> {
+        let [b0, b1, b2] = barycentric;
+        let face_normal = self.face_normal();
+        let front_face = r.direction.dot(face_normal) < 0.0;
+
+        let shading_normal = if let Some(n) = &self.normals {
+            let interpolated = n[0] * b0 + n[1] * b1 + n[2] * b2;
+            if interpolated.length_squared() > 0.0 {
+                interpolated
+            } else {
+                face_normal
+            }
+        } else {
+            face_normal
+        };
+        // keep the normal on the same side of the surface as the ray, like the other primitives
+        let normal = if front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+
+        let (u, v) = if let Some(uv) = &self.uvs {
+            (
+                uv[0].0 * b0 + uv[1].0 * b1 + uv[2].0 * b2,
+                uv[0].1 * b0 + uv[1].1 * b1 + uv[2].1 * b2,
+            )
+        } else {
+            (b1, b2)
+        };
+
+        Some(Hit {
+            point: self.vertices[0] * b0 + self.vertices[1] * b1 + self.vertices[2] * b2,
+            normal: normal.unit_vector(),
+            t,
+            u,
+            v,
+            front_face,
+            color: self.colors.map(|c| c[0] * b0 + c[1] * b1 + c[2] * b2),
+            material: Arc::clone(&self.material),
+        })
+    }
+}
+
+impl Hittable for Triangle {
+    // Watertight ray-triangle intersection (Woop, Benthin, and Wald 2013)
+    // Rays through a shared edge or vertex hit at least one of the triangles sharing it
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, _rng: &mut Pcg64Mcg) -> Option<Hit> {
+        let dir = r.direction;
+
+        // permute axes so the largest direction component is z, flipping winding if it's negative
+        let kz: u8 = if dir.x.abs() > dir.y.abs() {
+            if dir.x.abs() > dir.z.abs() {
+                0
+            } else {
+                2
+            }
+        } else if dir.y.abs() > dir.z.abs() {
+            1
+        } else {
+            2
+        };
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        if dir[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+
+        // shear so the ray points down +z
+        let sx = dir[kx] / dir[kz];
+        let sy = dir[ky] / dir[kz];
+        let sz = 1.0 / dir[kz];
+
+        // vertices relative to the ray origin
+        let p0 = self.vertices[0] - r.origin;
+        let p1 = self.vertices[1] - r.origin;
+        let p2 = self.vertices[2] - r.origin;
+
+        let ax = (-sx).mul_add(p0[kz], p0[kx]);
+        let ay = (-sy).mul_add(p0[kz], p0[ky]);
+        let bx = (-sx).mul_add(p1[kz], p1[kx]);
+        let by = (-sy).mul_add(p1[kz], p1[ky]);
+        let cx = (-sx).mul_add(p2[kz], p2[kx]);
+        let cy = (-sy).mul_add(p2[kz], p2[ky]);
+
+        // scaled barycentric coordinates from edge functions
+        let e0 = cx * by - cy * bx;
+        let e1 = ax * cy - ay * cx;
+        let e2 = bx * ay - by * ax;
+
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return None;
+        }
+
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            // ray is parallel to the triangle or the triangle is degenerate
+            return None;
+        }
+
+        let t_scaled = e0 * sz * p0[kz] + e1 * sz * p1[kz] + e2 * sz * p2[kz];
+        let t = t_scaled / det;
+        if !(t > t_min && t < t_max) {
+            return None;
+        }
+
+        self.compute_hit(r, t, [e0 / det, e1 / det, e2 / det])
+    }
+
+    fn bounding_box(&self, _: f64, _: f64) -> Option<AABB> {
+        let [v0, v1, v2] = self.vertices;
+        let padding = Vec3::from_xyz(PADDING, PADDING, PADDING);
+        Some(AABB {
+            min: Vec3 {
+                x: v0.x.min(v1.x).min(v2.x),
+                y: v0.y.min(v1.y).min(v2.y),
+                z: v0.z.min(v1.z).min(v2.z),
+            } - padding,
+            max: Vec3 {
+                x: v0.x.max(v1.x).max(v2.x),
+                y: v0.y.max(v1.y).max(v2.y),
+                z: v0.z.max(v1.z).max(v2.z),
+            } + padding,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Arc, Hittable, Ray, Triangle, Vec3};
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+    use rand_pcg::Pcg64Mcg;
+
+    fn triangle(vertices: [Vec3; 3]) -> Triangle {
+        Triangle::new(
+            vertices,
+            Arc::new(Lambertian::new(Box::new(SolidColor::from_rgb(
+                0.5, 0.5, 0.5,
+            )))),
+        )
+    }
+
+    #[test]
+    fn hit() {
+        let mut rng = Pcg64Mcg::new(0);
+        let mut tri = triangle([
+            Vec3::from_xyz(0.0, 0.0, 1.0),
+            Vec3::from_xyz(1.0, 0.0, 1.0),
+            Vec3::from_xyz(0.0, 1.0, 1.0),
+        ]);
+        let hit_ray = Ray {
+            origin: Vec3::from_xyz(0.25, 0.5, 0.0),
+            direction: Vec3::from_xyz(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let back_ray = Ray {
+            origin: Vec3::from_xyz(0.25, 0.5, 2.0),
+            direction: Vec3::from_xyz(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let miss_ray = Ray {
+            origin: Vec3::from_xyz(0.75, 0.5, 0.0),
+            direction: Vec3::from_xyz(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let parallel_ray = Ray {
+            origin: Vec3::from_xyz(-1.0, 0.25, 1.0),
+            direction: Vec3::from_xyz(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        // face normal is +z, so a ray heading +z hits the back
+        let hit = tri.hit(&hit_ray, 0.0, 10.0, &mut rng).unwrap();
+        assert_eq!(hit.point, Vec3::from_xyz(0.25, 0.5, 1.0));
+        assert_eq!(hit.normal, Vec3::from_xyz(0.0, 0.0, -1.0));
+        assert!((hit.t - 1.0).abs() < f64::EPSILON);
+        assert!((hit.u - 0.25).abs() < f64::EPSILON);
+        assert!((hit.v - 0.5).abs() < f64::EPSILON);
+        assert!(!hit.front_face);
+        assert_eq!(hit.color, None);
+
+        let hit = tri.hit(&back_ray, 0.0, 10.0, &mut rng).unwrap();
+        assert_eq!(hit.normal, Vec3::from_xyz(0.0, 0.0, 1.0));
+        assert!(hit.front_face);
+
+        assert!(tri.hit(&hit_ray, 0.0, 0.5, &mut rng).is_none());
+        assert!(tri.hit(&miss_ray, 0.0, 10.0, &mut rng).is_none());
+        assert!(tri.hit(&parallel_ray, 0.0, 10.0, &mut rng).is_none());
+
+        tri.uvs = Some([(0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]);
+        tri.normals = Some([
+            Vec3::from_xyz(0.0, 0.0, 1.0),
+            Vec3::from_xyz(1.0, 0.0, 1.0),
+            Vec3::from_xyz(0.0, 0.0, 1.0),
+        ]);
+        let hit = tri.hit(&back_ray, 0.0, 10.0, &mut rng).unwrap();
+        assert!((hit.u - 0.5).abs() < f64::EPSILON);
+        assert!((hit.v - 0.75).abs() < f64::EPSILON);
+        assert_eq!(hit.normal, Vec3::from_xyz(0.25, 0.0, 1.0).unit_vector());
+
+        tri.colors = Some([
+            Vec3::from_xyz(1.0, 0.0, 0.0),
+            Vec3::from_xyz(0.0, 1.0, 0.0),
+            Vec3::from_xyz(0.0, 0.0, 1.0),
+        ]);
+        let hit = tri.hit(&back_ray, 0.0, 10.0, &mut rng).unwrap();
+        assert_eq!(hit.color, Some(Vec3::from_xyz(0.25, 0.25, 0.5)));
+    }
+
+    #[test]
+    fn watertight() {
+        let mut rng = Pcg64Mcg::new(0);
+        // two triangles sharing the edge from (0 0 0) to (1 1 0)
+        let lower = triangle([
+            Vec3::from_xyz(0.0, 0.0, 0.0),
+            Vec3::from_xyz(1.0, 0.0, 0.0),
+            Vec3::from_xyz(1.0, 1.0, 0.0),
+        ]);
+        let upper = triangle([
+            Vec3::from_xyz(0.0, 0.0, 0.0),
+            Vec3::from_xyz(1.0, 1.0, 0.0),
+            Vec3::from_xyz(0.0, 1.0, 0.0),
+        ]);
+
+        for i in 1..100 {
+            let d = f64::from(i) / 100.0;
+            let r = Ray {
+                origin: Vec3::from_xyz(d - 0.3, d + 0.2, 1.0),
+                direction: Vec3::from_xyz(0.3, -0.2, -1.0),
+                time: 0.0,
+            };
+            assert!(
+                lower.hit(&r, 0.0, 10.0, &mut rng).is_some()
+                    || upper.hit(&r, 0.0, 10.0, &mut rng).is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn bounding_box() {
+        let tri = triangle([
+            Vec3::from_xyz(0.0, 0.0, 0.0),
+            Vec3::from_xyz(1.0, 0.0, 0.0),
+            Vec3::from_xyz(1.0, 0.0, 2.0),
+        ]);
+        let bb = tri.bounding_box(0.0, 1.0).unwrap();
+        assert!(bb.max.y > bb.min.y);
+        assert_eq!(bb.min, Vec3::from_xyz(-0.0001, -0.0001, -0.0001));
+        assert_eq!(bb.max, Vec3::from_xyz(1.0001, 0.0001, 2.0001));
+        let r = Ray {
+            origin: Vec3::from_xyz(0.9, 1.0, 0.5),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(bb.hit(&r, 0.0, 10.0));
+    }
+}
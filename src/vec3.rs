@@ -78,6 +78,7 @@ impl Vec3 {
         self.z
     }
 
+    #[allow(dead_code)]
     pub fn is_valid_color(&self, samples: u32) -> bool {
         let scale = 1.0 / f64::from(samples);
         let x = self.x * scale;
@@ -87,6 +88,11 @@ impl Vec3 {
         0.0 <= x && x <= 1.0 && 0.0 <= y && y <= 1.0 && 0.0 <= z && z <= 1.0
     }
 
+    // Emissive materials can push accumulated light past 1.0, but it should never be negative or NaN
+    pub fn is_valid_radiance(&self) -> bool {
+        self.x >= 0.0 && self.y >= 0.0 && self.z >= 0.0
+    }
+
     pub fn length(&self) -> f64 {
         self.length_squared().sqrt()
     }
@@ -400,6 +406,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::op_ref)]
     fn sub() {
         let a = Vec3::from_xyz(1.0, 2.0, 3.0);
         let b = Vec3::from_xyz(1.0, 3.0, 5.0);
@@ -455,6 +462,13 @@ mod test {
         assert!(!invalid.is_valid_color(10));
     }
 
+    #[test]
+    fn is_valid_radiance() {
+        assert!(Vec3::from_xyz(9.0, 10.2, 0.0).is_valid_radiance());
+        assert!(!Vec3::from_xyz(9.0, -0.1, 0.0).is_valid_radiance());
+        assert!(!Vec3::from_xyz(f64::NAN, 1.0, 0.0).is_valid_radiance());
+    }
+
     #[test]
     fn unit_vector() {
         let a = Vec3::from_xyz(5.0, 4.0, 3.0);
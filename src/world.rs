@@ -0,0 +1,175 @@
+use super::aabb::AABB;
+use super::bvh::{FlatBVH, Split};
+use super::hit::{Hit, Hittable};
+use super::ray::Ray;
+use super::vec3::Vec3;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+// Every object in a scene. Those with bounding boxes go in a BVH, and those without (like planes) are tested
+// one after the other alongside it.
+pub struct World {
+    // None when every object is unbounded
+    bounded: Option<FlatBVH>,
+    unbounded: Vec<Arc<dyn Hittable>>,
+    // Emitting objects that are also in objects, for sending rays towards directly
+    lights: Vec<Arc<dyn Hittable>>,
+}
+
+impl World {
+    pub fn new<T: Rng>(
+        rng: &mut T,
+        objects: Vec<Arc<dyn Hittable>>,
+        lights: Vec<Arc<dyn Hittable>>,
+        t0: f64,
+        t1: f64,
+        split: Split,
+    ) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|o| o.bounding_box(t0, t1).is_some());
+        Self {
+            bounded: if bounded.is_empty() {
+                None
+            } else {
+                Some(FlatBVH::new(rng, bounded, t0, t1, split))
+            },
+            unbounded,
+            lights,
+        }
+    }
+
+    pub fn has_lights(&self) -> bool {
+        !self.lights.is_empty()
+    }
+
+    // A direction from origin towards a random point on one of the lights, picked evenly
+    pub fn light_direction(&self, origin: Vec3, rng: &mut Pcg64Mcg) -> Option<Vec3> {
+        let light = &self.lights[rng.gen_range(0, self.lights.len())];
+        light.random_direction(origin, rng)
+    }
+
+    // Density over solid angle of light_direction picking direction, the average of every light's
+    #[allow(clippy::cast_precision_loss)]
+    pub fn light_pdf(&self, origin: Vec3, direction: Vec3, time: f64) -> f64 {
+        let total: f64 = self
+            .lights
+            .iter()
+            .map(|light| light.pdf_value(origin, direction, time))
+            .sum();
+        total / self.lights.len() as f64
+    }
+}
+
+impl Hittable for World {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rng: &mut Pcg64Mcg) -> Option<Hit> {
+        let mut hit = self
+            .bounded
+            .as_ref()
+            .and_then(|bvh| bvh.hit(r, t_min, t_max, rng));
+        let mut closest = hit.as_ref().map_or(t_max, |h| h.t);
+        for object in &self.unbounded {
+            if let Some(object_hit) = object.hit(r, t_min, closest, rng) {
+                closest = object_hit.t;
+                hit = Some(object_hit);
+            }
+        }
+        hit
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        if self.unbounded.is_empty() {
+            self.bounded.as_ref()?.bounding_box(t0, t1)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hittable, Split, World};
+    use crate::plane::Plane;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+    use rand_pcg::Pcg64Mcg;
+    use std::sync::Arc;
+
+    #[test]
+    fn unbounded() {
+        let sphere = |x: f64| -> Arc<dyn Hittable> {
+            Arc::new(Sphere {
+                center: Vec3::from_xyz(x, 1.0, 0.0),
+                radius: 1.0,
+                ..Sphere::default()
+            })
+        };
+        let ground = || -> Arc<dyn Hittable> {
+            Arc::new(
+                Plane::new(
+                    Vec3::new(),
+                    Vec3::from_xyz(0.0, 1.0, 0.0),
+                    Sphere::default().material,
+                )
+                .unwrap(),
+            )
+        };
+        let down = |x: f64| Ray {
+            origin: Vec3::from_xyz(x, 5.0, 0.0),
+            direction: Vec3::from_xyz(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let hit_t = |world: &World, x: f64| {
+            world
+                .hit(&down(x), 0.001, f64::INFINITY, &mut Pcg64Mcg::new(0))
+                .map(|h| h.t)
+        };
+
+        let world = World::new(
+            &mut Pcg64Mcg::new(1),
+            vec![sphere(0.0), ground(), sphere(4.0)],
+            vec![],
+            0.0,
+            1.0,
+            Split::SurfaceArea,
+        );
+        assert!(world.bounding_box(0.0, 1.0).is_none());
+        assert_eq!(hit_t(&world, 0.0), Some(3.0));
+        assert_eq!(hit_t(&world, 4.0), Some(3.0));
+        assert_eq!(hit_t(&world, 2.0), Some(5.0));
+
+        // the sphere is found in front of the plane when the plane would be hit first in the list
+        let world = World::new(
+            &mut Pcg64Mcg::new(1),
+            vec![ground(), sphere(0.0)],
+            vec![],
+            0.0,
+            1.0,
+            Split::Random,
+        );
+        assert_eq!(hit_t(&world, 0.0), Some(3.0));
+
+        let world = World::new(
+            &mut Pcg64Mcg::new(1),
+            vec![ground()],
+            vec![],
+            0.0,
+            1.0,
+            Split::SurfaceArea,
+        );
+        assert_eq!(hit_t(&world, 0.0), Some(5.0));
+
+        let world = World::new(
+            &mut Pcg64Mcg::new(1),
+            vec![sphere(0.0)],
+            vec![],
+            0.0,
+            1.0,
+            Split::SurfaceArea,
+        );
+        assert!(world.bounding_box(0.0, 1.0).is_some());
+        assert_eq!(hit_t(&world, 2.0), None);
+    }
+}